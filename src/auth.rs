@@ -0,0 +1,282 @@
+use crate::config::{AuthEntry, DockerConfig};
+use crate::error::Result;
+use anyhow::{anyhow, Context};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Key of Docker Hub credentials in `config.json`
+const DOCKER_HUB_SERVER: &str = "https://index.docker.io/v1/";
+
+/// Registry credentials sent to the daemon in `X-Registry-Auth` header
+///
+/// # Examples
+/// ```no_run
+/// use docker_helper::{DockerClient, RegistryAuth};
+///
+/// let client = DockerClient::from_env()
+///     .unwrap()
+///     .with_registry_auth("registry.example.com", RegistryAuth::password("ci", "secret"));
+/// let result = client.pull_image("registry.example.com/team/service:1.0");
+/// ```
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegistryAuth {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(rename = "serveraddress", skip_serializing_if = "Option::is_none")]
+    pub server_address: Option<String>,
+    #[serde(rename = "identitytoken", skip_serializing_if = "Option::is_none")]
+    pub identity_token: Option<String>,
+}
+
+impl RegistryAuth {
+    /// Username and password credentials
+    pub fn password(username: &str, password: &str) -> RegistryAuth {
+        RegistryAuth {
+            username: Some(username.to_owned()),
+            password: Some(password.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Identity (refresh) token credentials
+    pub fn identity_token(token: &str) -> RegistryAuth {
+        RegistryAuth {
+            identity_token: Some(token.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Base64 URL-safe encoded JSON expected by `X-Registry-Auth` header
+    ///
+    /// # Examples
+    /// ```
+    /// let auth = docker_helper::RegistryAuth::identity_token("token");
+    /// assert_eq!(auth.header_value().unwrap(), "eyJpZGVudGl0eXRva2VuIjoidG9rZW4ifQ==");
+    /// ```
+    pub fn header_value(&self) -> Result<String> {
+        Ok(URL_SAFE.encode(serde_json::to_vec(self)?))
+    }
+
+    /// Resolves credentials for a given `registry` host from Docker `config.json`: through
+    /// a credential helper configured in `credHelpers` or `credsStore`, falling back to `auths`
+    ///
+    /// # Examples
+    /// ```no_run
+    /// let auth = docker_helper::RegistryAuth::from_config("registry.example.com");
+    /// ```
+    pub fn from_config(registry: &str) -> Result<Option<RegistryAuth>> {
+        let config = DockerConfig::load()?;
+        let server = server_address(registry);
+
+        let helper = config
+            .cred_helpers
+            .iter()
+            .find(|(key, _)| same_registry(key, registry))
+            .map(|(_, helper)| helper)
+            .or(config.creds_store.as_ref())
+            .filter(|helper| !helper.is_empty());
+        if let Some(helper) = helper {
+            if let Some(auth) = RegistryAuth::from_helper(helper, &server)? {
+                return Ok(Some(auth));
+            }
+        }
+
+        let entry = config
+            .auths
+            .iter()
+            .find(|(key, _)| same_registry(key, registry));
+        match entry {
+            Some((_, entry)) => Ok(Some(RegistryAuth::from_entry(entry, &server)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Runs `docker-credential-<helper> get` passing `server` on stdin
+    fn from_helper(helper: &str, server: &str) -> Result<Option<RegistryAuth>> {
+        #[derive(Deserialize)]
+        struct HelperCredentials {
+            #[serde(rename = "Username")]
+            username: String,
+            #[serde(rename = "Secret")]
+            secret: String,
+        }
+
+        let program = format!("docker-credential-{}", helper);
+        let mut child = Command::new(&program)
+            .arg("get")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run credential helper {}", program))?;
+        child
+            .stdin
+            .take()
+            .context("Credential helper stdin is not available")?
+            .write_all(server.as_bytes())?;
+        let output = child.wait_with_output()?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() {
+            if stdout.contains("credentials not found") {
+                return Ok(None);
+            }
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let message = format!("{} {}", stdout.trim(), stderr.trim());
+            return Err(anyhow!(
+                "Credential helper {} failed for {}: {}",
+                program,
+                server,
+                message.trim()
+            )
+            .into());
+        }
+
+        let credentials: HelperCredentials = serde_json::from_str(&stdout)
+            .with_context(|| format!("Failed to parse {} output: {}", program, stdout))?;
+        let mut auth = if credentials.username == "<token>" {
+            RegistryAuth::identity_token(&credentials.secret)
+        } else {
+            RegistryAuth::password(&credentials.username, &credentials.secret)
+        };
+        auth.server_address = Some(server.to_owned());
+        Ok(Some(auth))
+    }
+
+    fn from_entry(entry: &AuthEntry, server: &str) -> Result<RegistryAuth> {
+        let mut auth = RegistryAuth {
+            username: entry.username.clone(),
+            password: entry.password.clone(),
+            email: entry.email.clone(),
+            server_address: Some(server.to_owned()),
+            identity_token: entry.identity_token.clone(),
+        };
+        if let Some(encoded) = entry.auth.as_deref().filter(|auth| !auth.is_empty()) {
+            let decoded = STANDARD
+                .decode(encoded)
+                .context("Failed to decode registry auth from Docker config")?;
+            let decoded = String::from_utf8(decoded)?;
+            let (username, password) = decoded
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Invalid registry auth for {} in Docker config", server))?;
+            auth.username = Some(username.to_owned());
+            auth.password = Some(password.to_owned());
+        }
+        Ok(auth)
+    }
+}
+
+/// Server address Docker CLI uses for a given registry host
+pub(crate) fn server_address(registry: &str) -> String {
+    if is_docker_hub(registry) {
+        DOCKER_HUB_SERVER.to_owned()
+    } else {
+        registry.to_owned()
+    }
+}
+
+/// Compares `config.json` key such as `https://registry.example.com/v1/` with registry host
+fn same_registry(key: &str, registry: &str) -> bool {
+    let host = key
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    host == registry || (is_docker_hub(host) && is_docker_hub(registry))
+}
+
+fn is_docker_hub(registry: &str) -> bool {
+    matches!(
+        registry,
+        "docker.io" | "index.docker.io" | "registry-1.docker.io"
+    )
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_support::{lock_env, TempDir};
+    use std::env;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    const HELPER: &str = r#"#!/bin/sh
+read server
+case "$server" in
+  https://index.docker.io/v1/) echo '{"ServerURL":"hub","Username":"<token>","Secret":"hub-token"}';;
+  localhost:5000) echo '{"ServerURL":"localhost:5000","Username":"ci","Secret":"secret"}';;
+  *) echo "credentials not found in native keychain"; exit 1;;
+esac
+"#;
+
+    /// Docker config directory with `docker-credential-stub` prepended to `PATH`,
+    /// the caller is expected to hold [lock_env]
+    fn helper_config(config: serde_json::Value) -> TempDir {
+        let dir = TempDir::new();
+        let helper = dir.write("bin/docker-credential-stub", HELPER);
+        fs::set_permissions(&helper, fs::Permissions::from_mode(0o755)).unwrap();
+        dir.write("config.json", &config.to_string());
+
+        let mut paths = vec![dir.path().join("bin")];
+        paths.extend(env::var_os("PATH").iter().flat_map(env::split_paths));
+        env::set_var("PATH", env::join_paths(paths).unwrap());
+        env::set_var("DOCKER_CONFIG", dir.path());
+        dir
+    }
+
+    #[test]
+    fn cred_helper_returns_password_credentials() {
+        let _env = lock_env();
+        let _config = helper_config(serde_json::json!({
+            "credHelpers": {"localhost:5000": "stub"},
+        }));
+
+        let auth = RegistryAuth::from_config("localhost:5000")
+            .unwrap()
+            .unwrap();
+        assert_eq!(auth.username.as_deref(), Some("ci"));
+        assert_eq!(auth.password.as_deref(), Some("secret"));
+        assert_eq!(auth.identity_token, None);
+        assert_eq!(auth.server_address.as_deref(), Some("localhost:5000"));
+    }
+
+    #[test]
+    fn cred_helper_token_username_is_identity_token() {
+        let _env = lock_env();
+        let _config = helper_config(serde_json::json!({"credsStore": "stub"}));
+
+        let auth = RegistryAuth::from_config("docker.io").unwrap().unwrap();
+        assert_eq!(auth.identity_token.as_deref(), Some("hub-token"));
+        assert_eq!(auth.username, None);
+        assert_eq!(auth.password, None);
+        assert_eq!(auth.server_address.as_deref(), Some(DOCKER_HUB_SERVER));
+    }
+
+    #[test]
+    fn cred_helper_not_found_falls_back_to_auths() {
+        let _env = lock_env();
+        let _config = helper_config(serde_json::json!({
+            "credsStore": "stub",
+            "auths": {"https://registry.example.com/v1/": {"auth": STANDARD.encode("alice:pw")}},
+        }));
+
+        let auth = RegistryAuth::from_config("registry.example.com")
+            .unwrap()
+            .unwrap();
+        assert_eq!(auth.username.as_deref(), Some("alice"));
+        assert_eq!(auth.password.as_deref(), Some("pw"));
+        assert_eq!(auth.server_address.as_deref(), Some("registry.example.com"));
+        assert_eq!(
+            RegistryAuth::from_config("other.example.com").unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,438 @@
+use crate::dockerignore::{clean_path, DockerIgnore};
+use crate::error::Result;
+use anyhow::{anyhow, Context};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tar::{Builder, Header};
+use walkdir::WalkDir;
+
+/// Dockerfile name used unless set with [BuildImageOptions::dockerfile]
+pub const DEFAULT_DOCKERFILE: &str = "Dockerfile";
+
+/// Image label holding [BuildImageOptions::content_hash] of images built with
+/// [BuildImageOptions::cache_by_content_hash]
+pub const CONTENT_HASH_LABEL: &str = "docker-helper.content-hash";
+
+/// Permissions of in-memory files added to the build context
+const MEMORY_FILE_MODE: u32 = 0o644;
+
+/// Options of [crate::build_image]: build context along with `/build` parameters
+///
+/// # Examples
+/// ```no_run
+/// use docker_helper::BuildImageOptions;
+///
+/// // Context directory, honouring its `.dockerignore`
+/// let options = BuildImageOptions::from_directory("tests/fixtures/app")
+///     .tag("test-app:latest")
+///     .build_arg("RUST_VERSION", "1.67")
+///     .target("runtime");
+///
+/// // In-memory Dockerfile with extra files
+/// let options = BuildImageOptions::from_dockerfile("FROM alpine:3.17\nCOPY init.sql /init.sql\n")
+///     .file("init.sql", "CREATE TABLE test (id INT);")
+///     .tag("test-db:latest")
+///     .label("com.example.suite", "integration");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BuildImageOptions {
+    /// Context directory, `None` when building from in-memory files only
+    pub context: Option<PathBuf>,
+    /// Dockerfile path relative to the context, [DEFAULT_DOCKERFILE] if not set
+    pub dockerfile: Option<String>,
+    /// In-memory Dockerfile taking precedence over the one in the context directory
+    pub dockerfile_contents: Option<String>,
+    /// In-memory files added to the context, keyed by `/` separated path
+    pub files: BTreeMap<String, Vec<u8>>,
+    /// Names of the built image in the form `image_name:version`
+    pub tags: Vec<String>,
+    pub build_args: BTreeMap<String, String>,
+    /// Stage of a multi-stage Dockerfile to build
+    pub target: Option<String>,
+    pub labels: BTreeMap<String, String>,
+    /// Do not use layer cache
+    pub no_cache: bool,
+    /// Always attempt to pull a newer version of base images
+    pub pull: bool,
+    /// Skip the build when an image with the same content hash exists locally,
+    /// unless `no_cache` or `pull` is set
+    pub cache_by_content_hash: bool,
+}
+
+impl BuildImageOptions {
+    /// Builds from a context directory, excluding paths matched by its `.dockerignore`
+    pub fn from_directory(context: impl Into<PathBuf>) -> BuildImageOptions {
+        BuildImageOptions {
+            context: Some(context.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Builds from an in-memory Dockerfile, see [BuildImageOptions::file] to add files
+    pub fn from_dockerfile(contents: &str) -> BuildImageOptions {
+        BuildImageOptions {
+            dockerfile_contents: Some(contents.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Dockerfile path relative to the context directory or absolute. Dockerfiles outside
+    /// of the context are added to it the same way Docker CLI does.
+    pub fn dockerfile(mut self, path: &str) -> BuildImageOptions {
+        self.dockerfile = Some(path.to_owned());
+        self
+    }
+
+    /// Adds in-memory file to the context, replacing the file with the same path if any
+    pub fn file(mut self, path: &str, contents: impl Into<Vec<u8>>) -> BuildImageOptions {
+        self.files.insert(clean_path(path), contents.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> BuildImageOptions {
+        self.tags.push(tag.to_owned());
+        self
+    }
+
+    /// Value of `ARG` instruction
+    pub fn build_arg(mut self, name: &str, value: &str) -> BuildImageOptions {
+        self.build_args.insert(name.to_owned(), value.to_owned());
+        self
+    }
+
+    pub fn target(mut self, target: &str) -> BuildImageOptions {
+        self.target = Some(target.to_owned());
+        self
+    }
+
+    pub fn label(mut self, name: &str, value: &str) -> BuildImageOptions {
+        self.labels.insert(name.to_owned(), value.to_owned());
+        self
+    }
+
+    pub fn no_cache(mut self, no_cache: bool) -> BuildImageOptions {
+        self.no_cache = no_cache;
+        self
+    }
+
+    pub fn pull(mut self, pull: bool) -> BuildImageOptions {
+        self.pull = pull;
+        self
+    }
+
+    /// Enables [BuildImageOptions::content_hash] based caching: the image is labelled with
+    /// [CONTENT_HASH_LABEL] and the build is skipped if an image with the same hash exists
+    /// unless [BuildImageOptions::no_cache] or [BuildImageOptions::pull] is set
+    pub fn cache_by_content_hash(mut self, enabled: bool) -> BuildImageOptions {
+        self.cache_by_content_hash = enabled;
+        self
+    }
+
+    /// Deterministic SHA-256 hex digest of everything affecting the build result: paths,
+    /// types, permissions and contents of context files after `.dockerignore` filtering,
+    /// Dockerfile path, build args, target stage and labels. Tags and flags are not included.
+    ///
+    /// # Examples
+    /// ```
+    /// use docker_helper::BuildImageOptions;
+    ///
+    /// let options = BuildImageOptions::from_dockerfile("FROM alpine:3.17\n");
+    /// let hash = options.content_hash().unwrap();
+    /// assert_eq!(hash, options.clone().tag("test:latest").content_hash().unwrap());
+    /// assert_ne!(hash, options.build_arg("A", "1").content_hash().unwrap());
+    /// ```
+    pub fn content_hash(&self) -> Result<String> {
+        let mut hasher = Sha256::new();
+        for (path, entry) in self.context_entries()? {
+            hash_field(&mut hasher, path.as_bytes());
+            match entry {
+                ContextEntry::Disk(source) => {
+                    let metadata = fs::symlink_metadata(&source)
+                        .with_context(|| format!("Failed to read {}", source.display()))?;
+                    hash_field(&mut hasher, &file_mode(&metadata).to_le_bytes());
+                    if metadata.is_symlink() {
+                        let target = fs::read_link(&source)?;
+                        hash_field(&mut hasher, b"symlink");
+                        hash_field(&mut hasher, target.to_string_lossy().as_bytes());
+                    } else if metadata.is_dir() {
+                        hash_field(&mut hasher, b"dir");
+                    } else {
+                        let contents = fs::read(&source)
+                            .with_context(|| format!("Failed to read {}", source.display()))?;
+                        hash_field(&mut hasher, b"file");
+                        hash_field(&mut hasher, &contents);
+                    }
+                }
+                ContextEntry::Memory(contents) => {
+                    hash_field(&mut hasher, &MEMORY_FILE_MODE.to_le_bytes());
+                    hash_field(&mut hasher, b"file");
+                    hash_field(&mut hasher, &contents);
+                }
+            }
+        }
+        let mut labels = self.labels.clone();
+        labels.remove(CONTENT_HASH_LABEL);
+        let parameters = serde_json::to_vec(&(
+            self.dockerfile_path()?,
+            &self.build_args,
+            &self.target,
+            &labels,
+        ))?;
+        hash_field(&mut hasher, &parameters);
+
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect())
+    }
+
+    /// Dockerfile path within the context as sent in `dockerfile` parameter of `/build`
+    pub(crate) fn dockerfile_path(&self) -> Result<String> {
+        Ok(self.resolve_dockerfile()?.0)
+    }
+
+    /// Dockerfile path within the context along with its contents if it lives outside of
+    /// the context directory. Like Docker CLI does, such a Dockerfile is added to the
+    /// context under a `.dockerfile.` prefixed name.
+    fn resolve_dockerfile(&self) -> Result<(String, Option<Vec<u8>>)> {
+        let dockerfile = self.dockerfile.as_deref().unwrap_or(DEFAULT_DOCKERFILE);
+        let path = Path::new(dockerfile);
+        let relative = match &self.context {
+            Some(context) if path.is_absolute() => relative_path(context, path).ok(),
+            _ if path.is_absolute() => None,
+            _ => Some(dockerfile.to_owned()),
+        };
+        if let Some(relative) = relative.as_deref().and_then(clean_relative_path) {
+            return Ok((relative, None));
+        }
+        if self.dockerfile_contents.is_some() {
+            return Err(anyhow!(
+                "In-memory Dockerfile path ({}) must be within the build context",
+                dockerfile
+            )
+            .into());
+        }
+
+        let source = match &self.context {
+            Some(context) => context.join(path),
+            None => path.to_owned(),
+        };
+        let contents = fs::read(&source)
+            .with_context(|| format!("Failed to read Dockerfile {}", source.display()))?;
+        let digest: String = Sha256::digest(&contents)
+            .iter()
+            .take(10)
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        Ok((format!(".dockerfile.{}", digest), Some(contents)))
+    }
+
+    /// Tar archive of the build context sent as `/build` request body
+    pub(crate) fn context_tar(&self) -> Result<Vec<u8>> {
+        let mut builder = Builder::new(Vec::new());
+        builder.follow_symlinks(false);
+        for (path, entry) in self.context_entries()? {
+            match entry {
+                ContextEntry::Disk(source) => builder
+                    .append_path_with_name(&source, &path)
+                    .with_context(|| format!("Failed to add {} to build context", path))?,
+                ContextEntry::Memory(contents) => append_file(&mut builder, &path, &contents)?,
+            }
+        }
+        Ok(builder.into_inner()?)
+    }
+
+    /// Files sent to the daemon keyed by their path within the context: the context
+    /// directory filtered by `.dockerignore`, overlaid with in-memory files
+    fn context_entries(&self) -> Result<BTreeMap<String, ContextEntry<'_>>> {
+        let (dockerfile, external_dockerfile) = self.resolve_dockerfile()?;
+        let mut result = BTreeMap::new();
+
+        if let Some(context) = &self.context {
+            let ignore = load_dockerignore(context, &dockerfile)?;
+            let mut entries = WalkDir::new(context)
+                .min_depth(1)
+                .sort_by_file_name()
+                .into_iter();
+            while let Some(entry) = entries.next() {
+                let entry = entry.with_context(|| {
+                    format!("Failed to read build context {}", context.display())
+                })?;
+                let path = relative_path(context, entry.path())?;
+                if ignore.is_ignored(&path) {
+                    if entry.file_type().is_dir() && !ignore.may_include_below(&path) {
+                        entries.skip_current_dir();
+                    }
+                    continue;
+                }
+                result.insert(path, ContextEntry::Disk(entry.into_path()));
+            }
+        }
+
+        if let Some(contents) = &self.dockerfile_contents {
+            result.insert(dockerfile, ContextEntry::Memory(contents.as_bytes().into()));
+        } else if let Some(contents) = external_dockerfile {
+            result.insert(dockerfile, ContextEntry::Memory(contents.into()));
+        }
+        for (path, contents) in &self.files {
+            result.insert(path.clone(), ContextEntry::Memory(contents.into()));
+        }
+        Ok(result)
+    }
+}
+
+enum ContextEntry<'a> {
+    Disk(PathBuf),
+    Memory(Cow<'a, [u8]>),
+}
+
+/// Length-prefixed so that adjacent fields cannot be confused
+fn hash_field(hasher: &mut Sha256, bytes: &[u8]) {
+    hasher.update((bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    if metadata.permissions().readonly() {
+        0o444
+    } else {
+        MEMORY_FILE_MODE
+    }
+}
+
+/// Cleans relative path, `None` if it points outside of the context
+fn clean_relative_path(path: &str) -> Option<String> {
+    let mut depth = 0usize;
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => depth = depth.checked_sub(1)?,
+            _ => depth += 1,
+        }
+    }
+    Some(clean_path(path)).filter(|path| !path.is_empty())
+}
+
+/// Reads `.dockerignore` of the context, always keeping files Docker CLI sends regardless
+fn load_dockerignore(context: &Path, dockerfile: &str) -> Result<DockerIgnore> {
+    let path = context.join(".dockerignore");
+    let mut ignore = match fs::read_to_string(&path) {
+        Ok(contents) => DockerIgnore::parse(&contents)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => DockerIgnore::default(),
+        Err(e) => {
+            return Err(anyhow!(e)
+                .context(format!("Failed to read {}", path.display()))
+                .into())
+        }
+    };
+    ignore.keep(dockerfile);
+    ignore.keep(".dockerignore");
+    Ok(ignore)
+}
+
+/// `/` separated path of `path` relative to the `context` directory
+fn relative_path(context: &Path, path: &Path) -> Result<String> {
+    let relative = path
+        .strip_prefix(context)
+        .with_context(|| format!("{} is outside of build context", path.display()))?;
+    let components: Vec<_> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+    Ok(components.join("/"))
+}
+
+fn append_file(builder: &mut Builder<Vec<u8>>, path: &str, contents: &[u8]) -> Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(MEMORY_FILE_MODE);
+    builder
+        .append_data(&mut header, path, contents)
+        .with_context(|| format!("Failed to add {} to build context", path))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn entry_paths(options: &BuildImageOptions) -> Vec<String> {
+        options.context_entries().unwrap().into_keys().collect()
+    }
+
+    #[test]
+    fn ignored_directories_are_skipped_unless_kept_path_is_inside() {
+        let context = TempDir::new();
+        context.write(".dockerignore", "target\ndocker\n*.md\n");
+        context.write("target/debug/app", "binary");
+        context.write("docker/Dockerfile", "FROM alpine:3.17\n");
+        context.write("docker/notes.txt", "notes");
+        context.write("src/main.rs", "fn main() {}");
+        context.write("README.md", "readme");
+
+        let options =
+            BuildImageOptions::from_directory(context.path()).dockerfile("docker/Dockerfile");
+        assert_eq!(
+            entry_paths(&options),
+            [".dockerignore", "docker/Dockerfile", "src", "src/main.rs"]
+        );
+
+        let ignore = load_dockerignore(context.path(), "docker/Dockerfile").unwrap();
+        assert!(!ignore.may_include_below("target"));
+        assert!(ignore.may_include_below("docker"));
+    }
+
+    #[test]
+    fn dockerfile_outside_context_is_added_to_context() {
+        let dir = TempDir::new();
+        dir.write("Dockerfile.test", "FROM alpine:3.17\nCOPY app /app\n");
+        dir.write("app/Dockerfile", "FROM scratch\n");
+        dir.write("app/app", "binary");
+        let context = dir.path().join("app");
+
+        let outside = BuildImageOptions::from_directory(&context).dockerfile("../Dockerfile.test");
+        let name = outside.dockerfile_path().unwrap();
+        assert!(name.starts_with(".dockerfile."));
+        assert_eq!(entry_paths(&outside), [name.as_str(), "Dockerfile", "app"]);
+        let default = BuildImageOptions::from_directory(&context);
+        assert_ne!(
+            outside.content_hash().unwrap(),
+            default.content_hash().unwrap()
+        );
+
+        let absolute = dir.path().join("Dockerfile.test");
+        let absolute =
+            BuildImageOptions::from_directory(&context).dockerfile(absolute.to_str().unwrap());
+        assert_eq!(absolute.dockerfile_path().unwrap(), name);
+
+        let inside = context.join("Dockerfile");
+        let inside =
+            BuildImageOptions::from_directory(&context).dockerfile(inside.to_str().unwrap());
+        assert_eq!(inside.dockerfile_path().unwrap(), "Dockerfile");
+    }
+
+    #[test]
+    fn in_memory_dockerfile_outside_context_is_rejected() {
+        let options =
+            BuildImageOptions::from_dockerfile("FROM alpine:3.17\n").dockerfile("../Dockerfile");
+        assert!(options.dockerfile_path().is_err());
+        assert!(options.context_tar().is_err());
+
+        let options = BuildImageOptions::from_dockerfile("FROM alpine:3.17\n")
+            .dockerfile("./build/../Dockerfile.test");
+        assert_eq!(options.dockerfile_path().unwrap(), "Dockerfile.test");
+    }
+}
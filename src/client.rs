@@ -0,0 +1,885 @@
+use crate::auth::{server_address, RegistryAuth};
+use crate::build::{BuildImageOptions, CONTENT_HASH_LABEL};
+use crate::container::{
+    default_resource_limits, CreateContainer, PortBinding, PortMapping, Protocol,
+};
+use crate::endpoint::Endpoint;
+use crate::error::{Error, Result};
+use crate::reference::{ImageReference, DEFAULT_TAG};
+use crate::transport::{CurlTransport, Method, Request, Response, Transport};
+use crate::types::*;
+use anyhow::{anyhow, Context};
+use serde_json::ser::to_string;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::BufReader;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Docker API client bound to a single daemon [Endpoint]
+///
+/// # Examples
+/// ```no_run
+/// use docker_helper::{DockerClient, Endpoint};
+///
+/// let client = DockerClient::new(Endpoint::Unix("/run/user/1000/docker.sock".into()));
+/// let result = client.pull_image("ubuntu:20.04");
+/// ```
+#[derive(Clone)]
+pub struct DockerClient {
+    transport: Arc<dyn Transport>,
+    registry_auths: HashMap<String, RegistryAuth>,
+}
+
+impl Default for DockerClient {
+    fn default() -> Self {
+        DockerClient::new(Endpoint::default())
+    }
+}
+
+impl fmt::Debug for DockerClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DockerClient").finish_non_exhaustive()
+    }
+}
+
+impl DockerClient {
+    /// Creates client talking to a given `endpoint` through [CurlTransport]
+    pub fn new(endpoint: Endpoint) -> DockerClient {
+        DockerClient::with_transport(CurlTransport::new(endpoint))
+    }
+
+    /// Creates client sending all Docker API calls through a custom `transport`
+    pub fn with_transport(transport: impl Transport + 'static) -> DockerClient {
+        DockerClient {
+            transport: Arc::new(transport),
+            registry_auths: HashMap::new(),
+        }
+    }
+
+    /// Uses `auth` for a given `registry` host instead of credentials from Docker config
+    ///
+    /// # Arguments
+    /// * `registry` - registry host, e.g. `registry.example.com:5000` or `docker.io`
+    /// * `auth` - credentials to send in `X-Registry-Auth` header
+    pub fn with_registry_auth(mut self, registry: &str, auth: RegistryAuth) -> DockerClient {
+        self.registry_auths.insert(registry.to_owned(), auth);
+        self
+    }
+
+    /// Creates client using `DOCKER_HOST`, the current Docker context or the default unix socket
+    ///
+    /// # Examples
+    /// ```no_run
+    /// let client = docker_helper::DockerClient::from_env().unwrap();
+    /// ```
+    pub fn from_env() -> Result<DockerClient> {
+        Ok(DockerClient::new(Endpoint::from_env()?))
+    }
+
+    /// See [crate::start_container_with_network_mode]
+    pub fn start_container_with_network_mode(
+        &self,
+        container_name: &str,
+        image: &str,
+        network_mode: &str,
+    ) -> Result<String> {
+        let config = CreateContainer::builder(image)
+            .network_mode(network_mode)
+            .build();
+        self.start_container_with_config(container_name, config)
+    }
+
+    /// See [crate::start_container_with_ports]
+    pub fn start_container_with_ports(
+        &self,
+        container_name: &str,
+        image: &str,
+        ports: &[PortMapping],
+    ) -> Result<String> {
+        let config = ports
+            .iter()
+            .cloned()
+            .fold(CreateContainer::builder(image), |builder, port| {
+                builder.publish(port)
+            })
+            .build();
+        self.start_container_with_config(container_name, config)
+    }
+
+    /// See [crate::start_container_with_config]
+    pub fn start_container_with_config(
+        &self,
+        container_name: &str,
+        config: CreateContainer,
+    ) -> Result<String> {
+        let existing_images = self.find_images(&config.image)?;
+        if existing_images.is_empty() {
+            self.pull_image(&config.image)?;
+        }
+
+        let id = self.create_container(container_name, config)?;
+        self.start_container(&id)?;
+        Ok(id)
+    }
+
+    /// See [crate::pull_image]
+    pub fn pull_image(&self, image_name: &str) -> Result<()> {
+        self.pull_image_with_progress(image_name, |_| {})
+    }
+
+    /// See [crate::pull_image_with_progress]
+    pub fn pull_image_with_progress(
+        &self,
+        image_name: &str,
+        on_progress: impl FnMut(&ProgressEvent),
+    ) -> Result<()> {
+        let reference = ImageReference::parse(image_name)?;
+        let mut request =
+            Request::new(Method::Post, "/images/create").query("fromImage", reference.name());
+        if let Some(tag) = reference.tag_or_digest() {
+            request = request.query("tag", tag);
+        }
+        if let Some(auth) = self.registry_auth(reference.registry())? {
+            request = request.header("X-Registry-Auth", auth.header_value()?);
+        }
+        self.send_progress(request, on_progress)
+    }
+
+    /// See [crate::tag_image]
+    pub fn tag_image(&self, source: &str, repo: &str, tag: &str) -> Result<()> {
+        let path = format!("/images/{}/tag", source);
+        let request = Request::new(Method::Post, path)
+            .query("repo", repo)
+            .query("tag", tag);
+        let _ = self.send_request(request)?;
+        Ok(())
+    }
+
+    /// See [crate::push_image]
+    pub fn push_image(&self, image_name: &str) -> Result<()> {
+        self.push_image_with_progress(image_name, |_| {})
+    }
+
+    /// See [crate::push_image_with_progress]
+    pub fn push_image_with_progress(
+        &self,
+        image_name: &str,
+        on_progress: impl FnMut(&ProgressEvent),
+    ) -> Result<()> {
+        let reference = ImageReference::parse(image_name)?;
+        // Without a tag the daemon would push every tag of the repository
+        if reference.digest().is_some() {
+            return Err(anyhow!(
+                "Cannot push image by digest ({}), push a tag instead",
+                image_name
+            )
+            .into());
+        }
+        let path = format!("/images/{}/push", reference.name());
+        let mut request = Request::new(Method::Post, path);
+        if let Some(tag) = reference.tag() {
+            request = request.query("tag", tag);
+        }
+        // Unlike pulls, pushes are rejected without the header even for anonymous access
+        let auth = self
+            .registry_auth(reference.registry())?
+            .unwrap_or_default();
+        request = request.header("X-Registry-Auth", auth.header_value()?);
+        self.send_progress(request, on_progress)
+    }
+
+    /// See [crate::build_image]
+    pub fn build_image(&self, options: &BuildImageOptions) -> Result<String> {
+        self.build_image_with_progress(options, |_| {})
+    }
+
+    /// See [crate::build_image_with_progress]
+    pub fn build_image_with_progress(
+        &self,
+        options: &BuildImageOptions,
+        mut on_progress: impl FnMut(&ProgressEvent),
+    ) -> Result<String> {
+        let mut labels = options.labels.clone();
+        if options.cache_by_content_hash {
+            let hash = options.content_hash()?;
+            // Both flags ask for a fresh build, e.g. to pick up updated base images
+            if !options.no_cache && !options.pull {
+                let label = format!("{}={}", CONTENT_HASH_LABEL, hash);
+                let cached = self.list_images(&ListImagesOptions::new().label(&label))?;
+                if let Some(image) = cached.into_iter().next() {
+                    for tag in &options.tags {
+                        let reference = ImageReference::parse(tag)?;
+                        let tag = reference.tag().unwrap_or(DEFAULT_TAG);
+                        self.tag_image(&image.id, &reference.familiar_name(), tag)?;
+                    }
+                    return Ok(image.id);
+                }
+            }
+            labels.insert(CONTENT_HASH_LABEL.to_owned(), hash);
+        }
+
+        let mut request =
+            Request::new(Method::Post, "/build").query("dockerfile", options.dockerfile_path()?);
+        for tag in &options.tags {
+            request = request.query("t", tag);
+        }
+        if !options.build_args.is_empty() {
+            request = request.query("buildargs", to_string(&options.build_args)?);
+        }
+        if let Some(target) = &options.target {
+            request = request.query("target", target);
+        }
+        if !labels.is_empty() {
+            request = request.query("labels", to_string(&labels)?);
+        }
+        if options.no_cache {
+            request = request.query("nocache", "true");
+        }
+        if options.pull {
+            request = request.query("pull", "true");
+        }
+        let request = request.body("application/x-tar", options.context_tar()?);
+
+        let mut image_id = None;
+        self.send_progress(request, |event| {
+            let id = event
+                .aux
+                .as_ref()
+                .and_then(|aux| aux.get("ID"))
+                .and_then(|id| id.as_str());
+            if let Some(id) = id {
+                image_id = Some(id.to_owned());
+            }
+            on_progress(event);
+        })?;
+        Ok(image_id.context("Docker daemon did not report ID of the built image")?)
+    }
+
+    /// See [crate::stop_and_cleanup_container]
+    pub fn stop_and_cleanup_container(&self, id: &str) -> Result<()> {
+        match self.stop_container(id) {
+            // Auto removed containers may be gone as soon as they exit
+            Err(e) if e.is_not_found() => return Ok(()),
+            Err(e) if !e.is_not_modified() => return Err(e),
+            _ => {}
+        }
+        match self.delete_container(id) {
+            // Auto removed containers are gone or being removed once stopped
+            Err(e) if e.is_not_found() => Ok(()),
+            Err(e) if e.is_conflict() && is_removal_in_progress(&e) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// See [crate::start_container]
+    pub fn start_container(&self, id: &str) -> Result<()> {
+        let path = format!("/containers/{}/start", id);
+        let _ = self.send_request(Request::new(Method::Post, path))?;
+        Ok(())
+    }
+
+    /// See [crate::stop_container]
+    pub fn stop_container(&self, id: &str) -> Result<()> {
+        let path = format!("/containers/{}/stop", id);
+        let _ = self.send_request(Request::new(Method::Post, path))?;
+        Ok(())
+    }
+
+    /// See [crate::delete_container]
+    pub fn delete_container(&self, id: &str) -> Result<()> {
+        let path = format!("/containers/{}", id);
+        let _ = self.send_request(Request::new(Method::Delete, path))?;
+        Ok(())
+    }
+
+    /// See [crate::prune_containers]
+    pub fn prune_containers(&self) -> Result<()> {
+        let _ = self.send_request(Request::new(Method::Post, "/containers/prune"));
+        Ok(())
+    }
+
+    /// See [crate::get_container_ip]
+    pub fn get_container_ip(&self, id: &str) -> Result<String> {
+        Ok(self
+            .find_containers(id)?
+            .first()
+            .context(format!("No containers found with ID = {}", id))?
+            .network_settings
+            .networks
+            .values()
+            .next()
+            .context(format!("No network found for container with ID = {}", id))?
+            .ip_address
+            .to_owned())
+    }
+
+    /// See [crate::wait_for_healthy]
+    pub fn wait_for_healthy(&self, id: &str, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let state = self.inspect_container(id)?.state;
+            let health = state
+                .health
+                .context(format!("Container with ID = {} has no healthcheck", id))?;
+            let last_output = health
+                .log
+                .last()
+                .map(|log| log.output.trim().to_owned())
+                .unwrap_or_default();
+            match health.status.as_str() {
+                "healthy" => return Ok(()),
+                "unhealthy" => {
+                    return Err(
+                        anyhow!("Container with ID = {} is unhealthy: {}", id, last_output).into(),
+                    )
+                }
+                _ if !state.running => {
+                    return Err(anyhow!(
+                        "Container with ID = {} is {} with exit code {}: {}",
+                        id,
+                        state.status,
+                        state.exit_code,
+                        last_output
+                    )
+                    .into())
+                }
+                _ => {}
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(anyhow!(
+                    "Container with ID = {} did not become healthy within {:?}: {}",
+                    id,
+                    timeout,
+                    last_output
+                )
+                .into());
+            }
+            thread::sleep(HEALTH_POLL_INTERVAL.min(deadline - now));
+        }
+    }
+
+    /// See [crate::get_host_address]
+    pub fn get_host_address(&self, id: &str) -> Result<String> {
+        let details = self.inspect_container(id)?;
+        if details.host_config.network_mode.as_deref() == Some("host") {
+            return Ok("127.0.0.1".to_owned());
+        }
+        let gateway = details
+            .network_settings
+            .networks
+            .values()
+            .map(|network| &network.gateway)
+            .find(|gateway| !gateway.is_empty())
+            .context(format!(
+                "No network gateway found for container with ID = {}",
+                id
+            ))?;
+        Ok(gateway.to_owned())
+    }
+
+    /// See [crate::get_host_port]
+    pub fn get_host_port(&self, id: &str, container_port: u16, protocol: Protocol) -> Result<u16> {
+        let key = PortMapping::new(container_port, protocol).key();
+        let port_map = self.get_port_map(id)?;
+        let port = port_map
+            .get(&key)
+            .into_iter()
+            .flatten()
+            .find_map(|binding| binding.host_port.parse().ok())
+            .context(format!(
+                "Port {} of container with ID = {} is not published",
+                key, id
+            ))?;
+        Ok(port)
+    }
+
+    /// See [crate::get_port_map]
+    pub fn get_port_map(&self, id: &str) -> Result<HashMap<String, Vec<PortBinding>>> {
+        Ok(self
+            .inspect_container(id)?
+            .network_settings
+            .ports
+            .into_iter()
+            .filter_map(|(port, bindings)| Some((port, bindings?)))
+            .collect())
+    }
+
+    /// See [crate::inspect_container]
+    pub fn inspect_container(&self, id: &str) -> Result<ContainerDetails> {
+        let path = format!("/containers/{}/json", id);
+        let resp = self.send_request(Request::new(Method::Get, path))?;
+        let result: ContainerDetails = serde_json::from_str(&resp).with_context(|| {
+            format!("Failed to parse inspect_container response json: {}", resp)
+        })?;
+        Ok(result)
+    }
+
+    /// See [crate::find_containers]
+    pub fn find_containers(&self, id: &str) -> Result<Vec<ContainerDescriptor>> {
+        self.list_containers(&ListContainersOptions::new().id(id))
+    }
+
+    /// See [crate::list_containers]
+    pub fn list_containers(
+        &self,
+        options: &ListContainersOptions,
+    ) -> Result<Vec<ContainerDescriptor>> {
+        let mut request = Request::new(Method::Get, "/containers/json")
+            .query("filters", to_string(&options.filters)?);
+        if options.all {
+            request = request.query("all", "true");
+        }
+        if let Some(limit) = options.limit {
+            request = request.query("limit", limit.to_string());
+        }
+        if options.size {
+            request = request.query("size", "true");
+        }
+        let resp = self.send_request(request)?;
+        let result: Vec<ContainerDescriptor> = serde_json::from_str(&resp)
+            .with_context(|| format!("Failed to parse list_containers response json: {}", resp))?;
+        Ok(result)
+    }
+
+    /// See [crate::find_images]
+    pub fn find_images(&self, reference: &str) -> Result<Vec<ImageDescriptor>> {
+        self.list_images(&ListImagesOptions::new().reference(reference))
+    }
+
+    /// See [crate::list_images]
+    pub fn list_images(&self, options: &ListImagesOptions) -> Result<Vec<ImageDescriptor>> {
+        let mut request = Request::new(Method::Get, "/images/json")
+            .query("filters", to_string(&options.filters)?);
+        if options.all {
+            request = request.query("all", "true");
+        }
+        let resp = self.send_request(request)?;
+        let result: Vec<ImageDescriptor> = serde_json::from_str(&resp)
+            .with_context(|| format!("Failed to parse list_images response json: {}", resp))?;
+        Ok(result)
+    }
+
+    /// See [crate::inspect_image]
+    pub fn inspect_image(&self, name: &str) -> Result<ImageDetails> {
+        let path = format!("/images/{}/json", name);
+        let resp = self.send_request(Request::new(Method::Get, path))?;
+        let result: ImageDetails = serde_json::from_str(&resp)
+            .with_context(|| format!("Failed to parse inspect_image response json: {}", resp))?;
+        Ok(result)
+    }
+
+    /// See [crate::remove_image]
+    pub fn remove_image(
+        &self,
+        name: &str,
+        options: &RemoveImageOptions,
+    ) -> Result<Vec<ImageDeleteItem>> {
+        let path = format!("/images/{}", name);
+        let request = Request::new(Method::Delete, path)
+            .query("force", options.force.to_string())
+            .query("noprune", options.noprune.to_string());
+        let resp = self.send_request(request)?;
+        let result: Vec<ImageDeleteItem> = serde_json::from_str(&resp)
+            .with_context(|| format!("Failed to parse remove_image response json: {}", resp))?;
+        Ok(result)
+    }
+
+    /// See [crate::create_container]
+    pub fn create_container(
+        &self,
+        container_name: &str,
+        mut request: CreateContainer,
+    ) -> Result<String> {
+        let defaults = default_resource_limits();
+        request.host_config.resources = request.host_config.resources.or(&defaults);
+        let request = Request::new(Method::Post, "/containers/create")
+            .query("name", container_name)
+            .json(&request)?;
+        let resp = self.send_request(request)?;
+        let result: CreateContainerResult = serde_json::from_str(&resp)
+            .with_context(|| format!("Failed to parse create_container response json: {}", resp))?;
+        Ok(result.id)
+    }
+
+    /// Credentials for a given `registry` host: explicitly configured ones or
+    /// those found in Docker config
+    fn registry_auth(&self, registry: &str) -> Result<Option<RegistryAuth>> {
+        let auth = match self.registry_auths.get(registry) {
+            Some(auth) => Some(auth.clone()),
+            None => RegistryAuth::from_config(registry)?,
+        };
+        Ok(auth.map(|mut auth| {
+            auth.server_address
+                .get_or_insert_with(|| server_address(registry));
+            auth
+        }))
+    }
+
+    /// Sends `request` failing on non-success response status
+    fn send(&self, request: Request) -> Result<Response> {
+        let path = request.path_and_query();
+        let response = self.transport.send(request)?;
+        match response.status {
+            200..=204 => Ok(response),
+            status => Err(Error::api(path, status, &response.text()?)),
+        }
+    }
+
+    /// Sends `request` returning response body as string
+    fn send_request(&self, request: Request) -> Result<String> {
+        self.send(request)?.text()
+    }
+
+    /// Sends `request` and feeds JSON progress stream of the response to `on_progress`,
+    /// failing on the first error reported by the daemon
+    fn send_progress(
+        &self,
+        request: Request,
+        mut on_progress: impl FnMut(&ProgressEvent),
+    ) -> Result<()> {
+        let path = request.path_and_query();
+        let response = self.send(request)?;
+        let events = serde_json::Deserializer::from_reader(BufReader::new(response.body))
+            .into_iter::<ProgressEvent>();
+        for event in events {
+            let event = event.with_context(|| format!("Failed to parse {} progress json", path))?;
+            if let Some(message) = event.error_message() {
+                return Err(Error::Stream {
+                    endpoint: path,
+                    message: message.to_owned(),
+                });
+            }
+            on_progress(&event);
+        }
+        Ok(())
+    }
+}
+
+/// Conflict reported when the daemon is already removing the container, e.g. auto removal
+fn is_removal_in_progress(error: &Error) -> bool {
+    error
+        .message()
+        .is_some_and(|message| message.contains("is already in progress"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::ResourceLimits;
+    use crate::test_support::{lock_default_limits, lock_empty_config, mock_client, sent};
+
+    const PULL_FAILURE: &str = concat!(
+        r#"{"status":"Pulling from library/missing","id":"1.0"}"#,
+        "\n",
+        r#"{"errorDetail":{"message":"manifest for missing:1.0 not found"},"error":"manifest for missing:1.0 not found"}"#,
+        "\n",
+    );
+
+    #[test]
+    fn pull_reports_stream_error_despite_success_status() {
+        let _env = lock_empty_config();
+        let (client, _) = mock_client(|_| Response::new(200, PULL_FAILURE));
+        let mut statuses = Vec::new();
+        let error = client
+            .pull_image_with_progress("missing:1.0", |event| statuses.push(event.status.clone()))
+            .unwrap_err();
+
+        match error {
+            Error::Stream { endpoint, message } => {
+                assert_eq!(
+                    endpoint,
+                    "/images/create?fromImage=docker.io%2Flibrary%2Fmissing&tag=1.0"
+                );
+                assert_eq!(message, "manifest for missing:1.0 not found");
+            }
+            other => panic!("expected stream error, got {:?}", other),
+        }
+        assert_eq!(statuses, [Some("Pulling from library/missing".to_owned())]);
+    }
+
+    #[test]
+    fn start_container_does_not_create_container_after_failed_pull() {
+        let _env = lock_empty_config();
+        let (client, requests) = mock_client(|request| match request.path.as_str() {
+            "/images/json" => Response::new(200, "[]"),
+            "/images/create" => Response::new(200, PULL_FAILURE),
+            _ => Response::new(201, r#"{"Id":"6fe66725ed81"}"#),
+        });
+        let config = CreateContainer::builder("missing:1.0").build();
+
+        let error = client
+            .start_container_with_config("test", config)
+            .unwrap_err();
+        assert!(matches!(error, Error::Stream { .. }));
+        let sent = sent(&requests);
+        assert_eq!(sent.len(), 2);
+        assert!(sent[1].starts_with("POST /images/create"));
+        assert!(!sent.iter().any(|request| request.contains("/containers/")));
+    }
+
+    #[test]
+    fn create_container_applies_default_resource_limits_unless_overridden() {
+        let _limits = lock_default_limits(ResourceLimits {
+            memory: Some(512),
+            pids_limit: Some(256),
+            ..Default::default()
+        });
+        let (client, requests) = mock_client(|_| Response::new(201, r#"{"Id":"6fe66725ed81"}"#));
+        let config = CreateContainer::builder("ubuntu:20.04")
+            .memory(1024)
+            .build();
+
+        assert_eq!(
+            client.create_container("test", config).unwrap(),
+            "6fe66725ed81"
+        );
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0].path_and_query(), "/containers/create?name=test");
+        let body: serde_json::Value =
+            serde_json::from_slice(requests[0].body.as_ref().unwrap()).unwrap();
+        let host_config = &body["HostConfig"];
+        assert_eq!(host_config["Memory"], 1024);
+        assert_eq!(host_config["PidsLimit"], 256);
+        assert!(host_config.get("NanoCpus").is_none());
+    }
+
+    #[test]
+    fn list_containers_sends_options_as_query() {
+        let (client, requests) = mock_client(|_| Response::new(200, "[]"));
+        let options = ListContainersOptions::new()
+            .all(true)
+            .limit(5)
+            .size(true)
+            .name("test")
+            .label("app=web")
+            .status("running");
+
+        assert!(client.list_containers(&options).unwrap().is_empty());
+        client
+            .list_containers(&ListContainersOptions::new())
+            .unwrap();
+        assert_eq!(
+            sent(&requests),
+            [
+                concat!(
+                    "GET /containers/json?filters=",
+                    "%7B%22name%22%3A%5B%22test%22%5D%2C%22label%22%3A%5B%22app%3Dweb%22%5D",
+                    "%2C%22status%22%3A%5B%22running%22%5D%7D&all=true&limit=5&size=true"
+                ),
+                "GET /containers/json?filters=%7B%7D",
+            ]
+        );
+    }
+
+    fn cleanup_requests(stop: Response, delete: Response) -> (Result<()>, Vec<String>) {
+        let responses = std::sync::Mutex::new(vec![delete, stop]);
+        let (client, requests) = mock_client(move |_| responses.lock().unwrap().pop().unwrap());
+        let result = client.stop_and_cleanup_container("x");
+        (result, sent(&requests))
+    }
+
+    #[test]
+    fn cleanup_of_already_removed_container_succeeds() {
+        let (result, sent) = cleanup_requests(
+            Response::new(404, r#"{"message":"No such container: x"}"#),
+            Response::new(204, ""),
+        );
+        result.unwrap();
+        assert_eq!(sent, ["POST /containers/x/stop"]);
+    }
+
+    #[test]
+    fn cleanup_of_container_being_removed_succeeds() {
+        let (result, sent) = cleanup_requests(
+            Response::new(204, ""),
+            Response::new(
+                409,
+                r#"{"message":"removal of container x is already in progress"}"#,
+            ),
+        );
+        result.unwrap();
+        assert_eq!(sent, ["POST /containers/x/stop", "DELETE /containers/x"]);
+    }
+
+    #[test]
+    fn cleanup_reports_other_conflicts() {
+        let (result, _) = cleanup_requests(
+            Response::new(304, ""),
+            Response::new(
+                409,
+                r#"{"message":"cannot remove container x: container is paused"}"#,
+            ),
+        );
+        assert!(result.unwrap_err().is_conflict());
+    }
+
+    fn inspect_with_health(health: &str) -> Response {
+        Response::new(
+            200,
+            format!(
+                r#"{{"Id":"x","Name":"/x","Created":"","Image":"sha256:1",
+                    "State":{{"Status":"running","Running":true,"ExitCode":0,"Health":{}}},
+                    "Config":{{}},"NetworkSettings":{{"Networks":{{}}}}}}"#,
+                health
+            ),
+        )
+    }
+
+    #[test]
+    fn wait_for_healthy_polls_until_first_probe_passes() {
+        let responses = std::sync::Mutex::new(vec![
+            inspect_with_health(
+                r#"{"Status":"healthy","FailingStreak":0,"Log":[{"Start":"","End":"","ExitCode":0,"Output":"ok"}]}"#,
+            ),
+            inspect_with_health(r#"{"Status":"starting","FailingStreak":0,"Log":null}"#),
+        ]);
+        let (client, requests) = mock_client(move |_| responses.lock().unwrap().pop().unwrap());
+
+        client
+            .wait_for_healthy("x", Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(sent(&requests).len(), 2);
+    }
+
+    #[test]
+    fn push_sends_tag_and_auth_and_reports_stream_error() {
+        let _env = lock_empty_config();
+        let (client, requests) = mock_client(|_| {
+            Response::new(
+                200,
+                concat!(
+                    r#"{"status":"The push refers to repository [localhost:5000/app]"}"#,
+                    "\n",
+                    r#"{"errorDetail":{"message":"denied: access forbidden"},"error":"denied"}"#,
+                    "\n",
+                ),
+            )
+        });
+        let client =
+            client.with_registry_auth("localhost:5000", RegistryAuth::password("ci", "pw"));
+
+        let error = client.push_image("localhost:5000/app:1.0").unwrap_err();
+        assert_eq!(error.message(), Some("denied: access forbidden"));
+        let requests = requests.lock().unwrap();
+        assert_eq!(
+            requests[0].path_and_query(),
+            "/images/localhost:5000/app/push?tag=1.0"
+        );
+        let (_, auth) = &requests[0].headers[0];
+        let expected = RegistryAuth {
+            server_address: Some("localhost:5000".to_owned()),
+            ..RegistryAuth::password("ci", "pw")
+        };
+        assert_eq!(auth, &expected.header_value().unwrap());
+    }
+
+    #[test]
+    fn push_succeeds_and_sends_empty_auth_without_credentials() {
+        let _env = lock_empty_config();
+        let (client, requests) = mock_client(|_| {
+            Response::new(
+                200,
+                r#"{"aux":{"Tag":"1.0","Digest":"sha256:0123","Size":528}}"#,
+            )
+        });
+
+        client.push_image("localhost:5000/app:1.0").unwrap();
+        let requests = requests.lock().unwrap();
+        assert_eq!(
+            requests[0].headers,
+            [("X-Registry-Auth".to_owned(), "e30=".to_owned())]
+        );
+    }
+
+    #[test]
+    fn push_by_digest_is_rejected() {
+        let _env = lock_empty_config();
+        let (client, requests) = mock_client(|_| Response::new(200, ""));
+
+        assert!(client.push_image("localhost:5000/app@sha256:0123").is_err());
+        assert!(client
+            .push_image("localhost:5000/app:1.0@sha256:0123")
+            .is_err());
+        assert!(sent(&requests).is_empty());
+    }
+
+    fn build_responses(cached: &'static str) -> impl Fn(&Request) -> Response {
+        move |request| match request.path.as_str() {
+            "/images/json" => Response::new(200, cached),
+            "/build" => Response::new(200, r#"{"aux":{"ID":"sha256:built"}}"#),
+            _ => Response::new(201, ""),
+        }
+    }
+
+    #[test]
+    fn content_hash_hit_tags_cached_image_without_building() {
+        let (client, requests) = mock_client(build_responses(r#"[{"Id":"sha256:cached"}]"#));
+        let options = BuildImageOptions::from_dockerfile("FROM alpine:3.17\n")
+            .tag("localhost:5000/app:1.0")
+            .tag("app")
+            .cache_by_content_hash(true);
+
+        assert_eq!(client.build_image(&options).unwrap(), "sha256:cached");
+        let filter = to_string(&ImageFilter {
+            label: vec![format!(
+                "{}={}",
+                CONTENT_HASH_LABEL,
+                options.content_hash().unwrap()
+            )],
+            ..Default::default()
+        })
+        .unwrap();
+        let list = Request::new(Method::Get, "/images/json").query("filters", filter);
+        assert_eq!(
+            sent(&requests),
+            [
+                format!("GET {}", list.path_and_query()),
+                "POST /images/sha256:cached/tag?repo=localhost%3A5000%2Fapp&tag=1.0".to_owned(),
+                "POST /images/sha256:cached/tag?repo=app&tag=latest".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn content_hash_miss_builds_labelled_image() {
+        let (client, requests) = mock_client(build_responses("[]"));
+        let options = BuildImageOptions::from_dockerfile("FROM alpine:3.17\n")
+            .tag("app")
+            .cache_by_content_hash(true);
+
+        assert_eq!(client.build_image(&options).unwrap(), "sha256:built");
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].path, "/build");
+        let labels = requests[1]
+            .query
+            .iter()
+            .find(|(name, _)| name == "labels")
+            .map(|(_, value)| value.clone());
+        let expected = format!(
+            r#"{{"{}":"{}"}}"#,
+            CONTENT_HASH_LABEL,
+            options.content_hash().unwrap()
+        );
+        assert_eq!(labels, Some(expected));
+    }
+
+    #[test]
+    fn content_hash_lookup_is_bypassed_by_no_cache_and_pull() {
+        let options =
+            BuildImageOptions::from_dockerfile("FROM alpine:3.17\n").cache_by_content_hash(true);
+        for options in [options.clone().no_cache(true), options.pull(true)] {
+            let (client, requests) = mock_client(build_responses(r#"[{"Id":"sha256:cached"}]"#));
+            assert_eq!(client.build_image(&options).unwrap(), "sha256:built");
+            let sent = sent(&requests);
+            assert_eq!(sent.len(), 1);
+            assert!(sent[0].starts_with("POST /build?"));
+        }
+    }
+}
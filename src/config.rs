@@ -0,0 +1,59 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+/// Subset of Docker CLI `config.json` used by this crate
+#[derive(Deserialize, Debug, Default)]
+pub(crate) struct DockerConfig {
+    #[serde(rename = "currentContext")]
+    pub current_context: Option<String>,
+    #[serde(default)]
+    pub auths: HashMap<String, AuthEntry>,
+    /// Credential helper used for all registries, e.g. `pass` for `docker-credential-pass`
+    #[serde(rename = "credsStore")]
+    pub creds_store: Option<String>,
+    /// Credential helpers for specific registries
+    #[serde(rename = "credHelpers", default)]
+    pub cred_helpers: HashMap<String, String>,
+}
+
+/// Credentials stored in `auths` section of `config.json`
+#[derive(Deserialize, Debug, Default)]
+pub(crate) struct AuthEntry {
+    /// Base64 encoded `username:password`
+    pub auth: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub email: Option<String>,
+    #[serde(rename = "identitytoken")]
+    pub identity_token: Option<String>,
+}
+
+impl DockerConfig {
+    /// Loads `config.json` from [config_dir], returning an empty config if it does not exist
+    pub(crate) fn load() -> Result<DockerConfig> {
+        let path = config_dir().join("config.json");
+        let data = match fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(DockerConfig::default()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        serde_json::from_str(&data)
+            .with_context(|| format!("Failed to parse Docker config {}", path.display()))
+    }
+}
+
+/// Docker CLI configuration directory: `DOCKER_CONFIG` or `~/.docker`
+pub(crate) fn config_dir() -> PathBuf {
+    match env::var_os("DOCKER_CONFIG") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => env::var_os("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_default()
+            .join(".docker"),
+    }
+}
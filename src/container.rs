@@ -0,0 +1,1054 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{PoisonError, RwLock};
+use std::time::Duration;
+
+/// Body of the container create call, see [CreateContainer::builder]
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CreateContainer {
+    #[serde(rename = "Image")]
+    pub image: String,
+    #[serde(rename = "Cmd", skip_serializing_if = "Option::is_none")]
+    pub cmd: Option<Vec<String>>,
+    #[serde(rename = "Entrypoint", skip_serializing_if = "Option::is_none")]
+    pub entrypoint: Option<Vec<String>>,
+    /// Environment variables in the form `KEY=value`
+    #[serde(rename = "Env", skip_serializing_if = "Option::is_none")]
+    pub env: Option<Vec<String>>,
+    #[serde(rename = "WorkingDir", skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    #[serde(rename = "User", skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(rename = "Labels", skip_serializing_if = "Option::is_none")]
+    pub labels: Option<HashMap<String, String>>,
+    #[serde(rename = "Hostname", skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(rename = "Domainname", skip_serializing_if = "Option::is_none")]
+    pub domainname: Option<String>,
+    /// Ports in the form `port/protocol`, e.g. `8080/tcp`
+    #[serde(rename = "ExposedPorts", skip_serializing_if = "Option::is_none")]
+    pub exposed_ports: Option<HashMap<String, EmptyObject>>,
+    #[serde(rename = "StopSignal", skip_serializing_if = "Option::is_none")]
+    pub stop_signal: Option<String>,
+    /// Seconds to wait for the container to stop before killing it
+    #[serde(rename = "StopTimeout", skip_serializing_if = "Option::is_none")]
+    pub stop_timeout: Option<i64>,
+    #[serde(rename = "Tty", skip_serializing_if = "Option::is_none")]
+    pub tty: Option<bool>,
+    #[serde(rename = "OpenStdin", skip_serializing_if = "Option::is_none")]
+    pub open_stdin: Option<bool>,
+    #[serde(rename = "Healthcheck", skip_serializing_if = "Option::is_none")]
+    pub healthcheck: Option<Healthcheck>,
+    #[serde(rename = "HostConfig")]
+    pub host_config: HostConfig,
+}
+
+/// Container healthcheck, overriding image `HEALTHCHECK`
+///
+/// # Examples
+/// ```
+/// use docker_helper::Healthcheck;
+/// use std::time::Duration;
+///
+/// let healthcheck = Healthcheck::shell("pg_isready -U postgres")
+///     .interval(Duration::from_secs(1))
+///     .timeout(Duration::from_secs(5))
+///     .retries(30)
+///     .start_period(Duration::from_secs(2));
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Healthcheck {
+    /// `["CMD", args...]`, `["CMD-SHELL", command]` or `["NONE"]`
+    #[serde(rename = "Test", skip_serializing_if = "Option::is_none")]
+    pub test: Option<Vec<String>>,
+    #[serde(
+        rename = "Interval",
+        with = "duration_nanos",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub interval: Option<Duration>,
+    #[serde(
+        rename = "Timeout",
+        with = "duration_nanos",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub timeout: Option<Duration>,
+    /// Consecutive failures needed to consider the container unhealthy
+    #[serde(rename = "Retries", skip_serializing_if = "Option::is_none")]
+    pub retries: Option<i64>,
+    /// Initialization time during which failures are not counted
+    #[serde(
+        rename = "StartPeriod",
+        with = "duration_nanos",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start_period: Option<Duration>,
+}
+
+impl Healthcheck {
+    /// Runs command directly, e.g. `["curl", "-f", "http://localhost"]`
+    pub fn cmd<I, S>(args: I) -> Healthcheck
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let test = std::iter::once("CMD".to_owned())
+            .chain(args.into_iter().map(Into::into))
+            .collect();
+        Healthcheck {
+            test: Some(test),
+            ..Default::default()
+        }
+    }
+
+    /// Runs command with the container default shell
+    pub fn shell(command: &str) -> Healthcheck {
+        Healthcheck {
+            test: Some(vec!["CMD-SHELL".to_owned(), command.to_owned()]),
+            ..Default::default()
+        }
+    }
+
+    /// Disables healthcheck inherited from the image
+    pub fn none() -> Healthcheck {
+        Healthcheck {
+            test: Some(vec!["NONE".to_owned()]),
+            ..Default::default()
+        }
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Healthcheck {
+        self.interval = Some(interval);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Healthcheck {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn retries(mut self, retries: i64) -> Healthcheck {
+        self.retries = Some(retries);
+        self
+    }
+
+    pub fn start_period(mut self, start_period: Duration) -> Healthcheck {
+        self.start_period = Some(start_period);
+        self
+    }
+}
+
+/// Docker API durations are expressed in nanoseconds
+mod duration_nanos {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(duration) => serializer.serialize_i64(duration.as_nanos() as i64),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        let nanos = Option::<i64>::deserialize(deserializer)?;
+        Ok(nanos.map(|nanos| Duration::from_nanos(nanos.max(0) as u64)))
+    }
+}
+
+/// Host specific part of container configuration
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HostConfig {
+    #[serde(rename = "NetworkMode", skip_serializing_if = "Option::is_none")]
+    pub network_mode: Option<String>,
+    /// Host bindings keyed by container port in the form `port/protocol`
+    #[serde(rename = "PortBindings", skip_serializing_if = "Option::is_none")]
+    pub port_bindings: Option<HashMap<String, Vec<PortBinding>>>,
+    /// Publishes all exposed ports to random host ports
+    #[serde(rename = "PublishAllPorts", skip_serializing_if = "Option::is_none")]
+    pub publish_all_ports: Option<bool>,
+    /// Bind mounts in the form `source:target[:options]`, see [BindMount]
+    #[serde(rename = "Binds", skip_serializing_if = "Option::is_none")]
+    pub binds: Option<Vec<String>>,
+    #[serde(rename = "Mounts", skip_serializing_if = "Option::is_none")]
+    pub mounts: Option<Vec<Mount>>,
+    #[serde(flatten)]
+    pub resources: ResourceLimits,
+    /// Kernel capabilities to add, e.g. `NET_ADMIN`
+    #[serde(rename = "CapAdd", skip_serializing_if = "Option::is_none")]
+    pub cap_add: Option<Vec<String>>,
+    /// Kernel capabilities to drop, e.g. `ALL`
+    #[serde(rename = "CapDrop", skip_serializing_if = "Option::is_none")]
+    pub cap_drop: Option<Vec<String>>,
+    #[serde(rename = "Privileged", skip_serializing_if = "Option::is_none")]
+    pub privileged: Option<bool>,
+    #[serde(rename = "ReadonlyRootfs", skip_serializing_if = "Option::is_none")]
+    pub readonly_rootfs: Option<bool>,
+    /// Security options, see [SecurityOption]
+    #[serde(rename = "SecurityOpt", skip_serializing_if = "Option::is_none")]
+    pub security_opt: Option<Vec<String>>,
+    /// User namespace mode, e.g. `host`
+    #[serde(rename = "UsernsMode", skip_serializing_if = "Option::is_none")]
+    pub userns_mode: Option<String>,
+    /// Runs an init process inside the container forwarding signals and reaping processes
+    #[serde(rename = "Init", skip_serializing_if = "Option::is_none")]
+    pub init: Option<bool>,
+    #[serde(rename = "RestartPolicy", skip_serializing_if = "Option::is_none")]
+    pub restart_policy: Option<RestartPolicy>,
+    /// Removes the container once it exits
+    #[serde(rename = "AutoRemove", skip_serializing_if = "Option::is_none")]
+    pub auto_remove: Option<bool>,
+    /// Additional `/etc/hosts` entries in the form `host:ip`, where `ip` may be
+    /// [HOST_GATEWAY] to resolve to the host
+    #[serde(rename = "ExtraHosts", skip_serializing_if = "Option::is_none")]
+    pub extra_hosts: Option<Vec<String>>,
+    /// DNS servers
+    #[serde(rename = "Dns", skip_serializing_if = "Option::is_none")]
+    pub dns: Option<Vec<String>>,
+    /// DNS search domains
+    #[serde(rename = "DnsSearch", skip_serializing_if = "Option::is_none")]
+    pub dns_search: Option<Vec<String>>,
+    /// `resolv.conf` options, e.g. `ndots:1`
+    #[serde(rename = "DnsOptions", skip_serializing_if = "Option::is_none")]
+    pub dns_options: Option<Vec<String>>,
+}
+
+/// Special [HostConfig::extra_hosts] address resolved by the daemon to the host gateway
+pub const HOST_GATEWAY: &str = "host-gateway";
+
+/// Behavior of the daemon when the container exits
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestartPolicy {
+    #[serde(rename = "Name", default)]
+    pub name: RestartPolicyName,
+    /// Number of restarts before giving up, only used with [RestartPolicyName::OnFailure]
+    #[serde(rename = "MaximumRetryCount", skip_serializing_if = "Option::is_none")]
+    pub maximum_retry_count: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RestartPolicyName {
+    #[default]
+    #[serde(rename = "no", alias = "")]
+    No,
+    #[serde(rename = "on-failure")]
+    OnFailure,
+    #[serde(rename = "always")]
+    Always,
+    #[serde(rename = "unless-stopped")]
+    UnlessStopped,
+}
+
+impl RestartPolicy {
+    /// Never restart
+    pub fn no() -> RestartPolicy {
+        RestartPolicy::default()
+    }
+
+    /// Restart on non-zero exit code at most `maximum_retry_count` times, `0` for no limit
+    pub fn on_failure(maximum_retry_count: i64) -> RestartPolicy {
+        RestartPolicy {
+            name: RestartPolicyName::OnFailure,
+            maximum_retry_count: Some(maximum_retry_count),
+        }
+    }
+
+    /// Always restart
+    pub fn always() -> RestartPolicy {
+        RestartPolicy {
+            name: RestartPolicyName::Always,
+            maximum_retry_count: None,
+        }
+    }
+
+    /// Always restart unless the container was explicitly stopped
+    pub fn unless_stopped() -> RestartPolicy {
+        RestartPolicy {
+            name: RestartPolicyName::UnlessStopped,
+            maximum_retry_count: None,
+        }
+    }
+}
+
+/// Entry of `HostConfig.SecurityOpt`
+///
+/// # Examples
+/// ```
+/// use docker_helper::SecurityOption;
+///
+/// assert_eq!(SecurityOption::NoNewPrivileges.to_string(), "no-new-privileges:true");
+/// assert_eq!(SecurityOption::AppArmor("docker-default".into()).to_string(), "apparmor=docker-default");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecurityOption {
+    /// Seccomp profile JSON (content, not path) or `unconfined`
+    Seccomp(String),
+    /// AppArmor profile name or `unconfined`
+    AppArmor(String),
+    /// SELinux label option, e.g. `type:container_t` or `disable`
+    Label(String),
+    /// Prevents processes from gaining new privileges
+    NoNewPrivileges,
+}
+
+impl fmt::Display for SecurityOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecurityOption::Seccomp(profile) => write!(f, "seccomp={}", profile),
+            SecurityOption::AppArmor(profile) => write!(f, "apparmor={}", profile),
+            SecurityOption::Label(label) => write!(f, "label={}", label),
+            SecurityOption::NoNewPrivileges => f.write_str("no-new-privileges:true"),
+        }
+    }
+}
+
+/// Resource limits of a container. Unset fields fall back to [default_resource_limits].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Memory limit in bytes
+    #[serde(rename = "Memory", skip_serializing_if = "Option::is_none")]
+    pub memory: Option<i64>,
+    /// Total memory plus swap limit in bytes, `-1` for unlimited swap
+    #[serde(rename = "MemorySwap", skip_serializing_if = "Option::is_none")]
+    pub memory_swap: Option<i64>,
+    /// CPU quota in units of 10<sup>-9</sup> CPUs
+    #[serde(rename = "NanoCpus", skip_serializing_if = "Option::is_none")]
+    pub nano_cpus: Option<i64>,
+    /// Relative CPU weight
+    #[serde(rename = "CpuShares", skip_serializing_if = "Option::is_none")]
+    pub cpu_shares: Option<i64>,
+    /// CPUs allowed to run the container, e.g. `0-3` or `0,1`
+    #[serde(rename = "CpusetCpus", skip_serializing_if = "Option::is_none")]
+    pub cpuset_cpus: Option<String>,
+    /// Maximum number of processes, `-1` for unlimited
+    #[serde(rename = "PidsLimit", skip_serializing_if = "Option::is_none")]
+    pub pids_limit: Option<i64>,
+    /// Size of `/dev/shm` in bytes
+    #[serde(rename = "ShmSize", skip_serializing_if = "Option::is_none")]
+    pub shm_size: Option<i64>,
+    #[serde(rename = "Ulimits", skip_serializing_if = "Option::is_none")]
+    pub ulimits: Option<Vec<Ulimit>>,
+}
+
+impl ResourceLimits {
+    /// Fills unset fields from `defaults`
+    pub fn or(self, defaults: &ResourceLimits) -> ResourceLimits {
+        ResourceLimits {
+            memory: self.memory.or(defaults.memory),
+            memory_swap: self.memory_swap.or(defaults.memory_swap),
+            nano_cpus: self.nano_cpus.or(defaults.nano_cpus),
+            cpu_shares: self.cpu_shares.or(defaults.cpu_shares),
+            cpuset_cpus: self.cpuset_cpus.or_else(|| defaults.cpuset_cpus.clone()),
+            pids_limit: self.pids_limit.or(defaults.pids_limit),
+            shm_size: self.shm_size.or(defaults.shm_size),
+            ulimits: self.ulimits.or_else(|| defaults.ulimits.clone()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Ulimit {
+    /// Limit name, e.g. `nofile`
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Soft")]
+    pub soft: i64,
+    #[serde(rename = "Hard")]
+    pub hard: i64,
+}
+
+static DEFAULT_RESOURCE_LIMITS: RwLock<ResourceLimits> = RwLock::new(ResourceLimits {
+    memory: None,
+    memory_swap: None,
+    nano_cpus: None,
+    cpu_shares: None,
+    cpuset_cpus: None,
+    pids_limit: None,
+    shm_size: None,
+    ulimits: None,
+});
+
+/// Sets resource limits applied to every container created by this crate unless
+/// the container configuration sets them explicitly. Use `0` (or `-1` where documented)
+/// in a container configuration to lift a default limit.
+///
+/// # Examples
+/// ```
+/// use docker_helper::ResourceLimits;
+///
+/// docker_helper::set_default_resource_limits(ResourceLimits {
+///     memory: Some(512 * 1024 * 1024),
+///     pids_limit: Some(256),
+///     ..Default::default()
+/// });
+/// assert_eq!(docker_helper::default_resource_limits().pids_limit, Some(256));
+/// ```
+pub fn set_default_resource_limits(limits: ResourceLimits) {
+    *DEFAULT_RESOURCE_LIMITS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = limits;
+}
+
+/// Resource limits applied to every created container, see [set_default_resource_limits]
+pub fn default_resource_limits() -> ResourceLimits {
+    DEFAULT_RESOURCE_LIMITS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Host side of a published container port
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortBinding {
+    /// Host IP to bind to, all interfaces if empty
+    #[serde(rename = "HostIp", default, skip_serializing_if = "String::is_empty")]
+    pub host_ip: String,
+    /// Host port, assigned by the daemon if empty
+    #[serde(rename = "HostPort", default)]
+    pub host_port: String,
+}
+
+/// Transport protocol of a container port
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    #[default]
+    Tcp,
+    Udp,
+    Sctp,
+}
+
+impl Protocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+            Protocol::Sctp => "sctp",
+        }
+    }
+}
+
+/// Container port published to the host
+///
+/// # Examples
+/// ```
+/// use docker_helper::PortMapping;
+///
+/// // 5432/tcp published to 127.0.0.1:15432
+/// let fixed = PortMapping::tcp(5432).host_port(15432).host_ip("127.0.0.1");
+/// // 53/udp published to a port chosen by the daemon
+/// let assigned = PortMapping::udp(53);
+/// assert_eq!(assigned.key(), "53/udp");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortMapping {
+    pub container_port: u16,
+    pub protocol: Protocol,
+    /// Host port, assigned by the daemon if `None`
+    pub host_port: Option<u16>,
+    /// Host IP to bind to, all interfaces if `None`
+    pub host_ip: Option<String>,
+}
+
+impl PortMapping {
+    pub fn new(container_port: u16, protocol: Protocol) -> PortMapping {
+        PortMapping {
+            container_port,
+            protocol,
+            host_port: None,
+            host_ip: None,
+        }
+    }
+
+    pub fn tcp(container_port: u16) -> PortMapping {
+        PortMapping::new(container_port, Protocol::Tcp)
+    }
+
+    pub fn udp(container_port: u16) -> PortMapping {
+        PortMapping::new(container_port, Protocol::Udp)
+    }
+
+    /// Binds to a fixed host port instead of the one assigned by the daemon
+    pub fn host_port(mut self, host_port: u16) -> PortMapping {
+        self.host_port = Some(host_port);
+        self
+    }
+
+    /// Binds to a given host IP only
+    pub fn host_ip(mut self, host_ip: &str) -> PortMapping {
+        self.host_ip = Some(host_ip.to_owned());
+        self
+    }
+
+    /// Container port in the form `port/protocol` used as a key by Docker API
+    pub fn key(&self) -> String {
+        format!("{}/{}", self.container_port, self.protocol.as_str())
+    }
+}
+
+/// Bind mount of a host path expressed in `Binds` syntax, which unlike [Mount]
+/// supports SELinux relabeling
+///
+/// # Examples
+/// ```
+/// use docker_helper::{BindMount, SelinuxLabel};
+///
+/// let bind = BindMount::new("/srv/pg/conf", "/etc/postgresql")
+///     .read_only()
+///     .selinux_label(SelinuxLabel::Private);
+/// assert_eq!(bind.to_string(), "/srv/pg/conf:/etc/postgresql:ro,Z");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindMount {
+    pub source: String,
+    pub target: String,
+    pub read_only: bool,
+    pub selinux_label: Option<SelinuxLabel>,
+}
+
+/// SELinux relabeling of bind mounted content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelinuxLabel {
+    /// `z`: content is shared between containers
+    Shared,
+    /// `Z`: content is private to the container
+    Private,
+}
+
+impl BindMount {
+    pub fn new(source: &str, target: &str) -> BindMount {
+        BindMount {
+            source: source.to_owned(),
+            target: target.to_owned(),
+            read_only: false,
+            selinux_label: None,
+        }
+    }
+
+    pub fn read_only(mut self) -> BindMount {
+        self.read_only = true;
+        self
+    }
+
+    pub fn selinux_label(mut self, label: SelinuxLabel) -> BindMount {
+        self.selinux_label = Some(label);
+        self
+    }
+}
+
+impl fmt::Display for BindMount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.source, self.target)?;
+        let mut options = Vec::new();
+        if self.read_only {
+            options.push("ro");
+        }
+        match self.selinux_label {
+            Some(SelinuxLabel::Shared) => options.push("z"),
+            Some(SelinuxLabel::Private) => options.push("Z"),
+            None => {}
+        }
+        if !options.is_empty() {
+            write!(f, ":{}", options.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Mount specification of `HostConfig.Mounts`
+///
+/// # Examples
+/// ```
+/// use docker_helper::Mount;
+///
+/// let data = Mount::volume("pg-data", "/var/lib/postgresql/data");
+/// let config = Mount::bind("/srv/pg/pg.conf", "/etc/postgresql/pg.conf").read_only();
+/// let scratch = Mount::tmpfs("/tmp").size_bytes(64 * 1024 * 1024).mode(0o1777);
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    #[serde(rename = "Target")]
+    pub target: String,
+    /// Host path for bind mounts, volume name for volumes, empty for tmpfs
+    #[serde(rename = "Source", default, skip_serializing_if = "String::is_empty")]
+    pub source: String,
+    #[serde(rename = "Type")]
+    pub mount_type: MountType,
+    #[serde(rename = "ReadOnly", skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<bool>,
+    #[serde(rename = "BindOptions", skip_serializing_if = "Option::is_none")]
+    pub bind_options: Option<BindOptions>,
+    #[serde(rename = "VolumeOptions", skip_serializing_if = "Option::is_none")]
+    pub volume_options: Option<VolumeOptions>,
+    #[serde(rename = "TmpfsOptions", skip_serializing_if = "Option::is_none")]
+    pub tmpfs_options: Option<TmpfsOptions>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MountType {
+    Bind,
+    Volume,
+    Tmpfs,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct BindOptions {
+    /// Mount propagation, e.g. `rprivate` or `rshared`
+    #[serde(rename = "Propagation", skip_serializing_if = "Option::is_none")]
+    pub propagation: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct VolumeOptions {
+    /// Do not populate a new volume with data from the image
+    #[serde(rename = "NoCopy", skip_serializing_if = "Option::is_none")]
+    pub no_copy: Option<bool>,
+    #[serde(rename = "Labels", skip_serializing_if = "Option::is_none")]
+    pub labels: Option<HashMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct TmpfsOptions {
+    #[serde(rename = "SizeBytes", skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<i64>,
+    /// File mode, e.g. `0o1777`
+    #[serde(rename = "Mode", skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+}
+
+impl Mount {
+    fn new(mount_type: MountType, source: &str, target: &str) -> Mount {
+        Mount {
+            target: target.to_owned(),
+            source: source.to_owned(),
+            mount_type,
+            read_only: None,
+            bind_options: None,
+            volume_options: None,
+            tmpfs_options: None,
+        }
+    }
+
+    /// Mounts host path `source` at `target`
+    pub fn bind(source: &str, target: &str) -> Mount {
+        Mount::new(MountType::Bind, source, target)
+    }
+
+    /// Mounts named volume at `target`, creating the volume if it does not exist
+    pub fn volume(name: &str, target: &str) -> Mount {
+        Mount::new(MountType::Volume, name, target)
+    }
+
+    /// Mounts in-memory tmpfs at `target`
+    pub fn tmpfs(target: &str) -> Mount {
+        Mount::new(MountType::Tmpfs, "", target)
+    }
+
+    pub fn read_only(mut self) -> Mount {
+        self.read_only = Some(true);
+        self
+    }
+
+    /// Size limit of tmpfs mount
+    pub fn size_bytes(mut self, size_bytes: i64) -> Mount {
+        self.tmpfs_options
+            .get_or_insert_with(Default::default)
+            .size_bytes = Some(size_bytes);
+        self
+    }
+
+    /// File mode of tmpfs mount, e.g. `0o1777`
+    pub fn mode(mut self, mode: u32) -> Mount {
+        self.tmpfs_options.get_or_insert_with(Default::default).mode = Some(mode);
+        self
+    }
+}
+
+/// Empty JSON object `{}` used as a value of Docker API sets such as `ExposedPorts`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmptyObject {}
+
+impl CreateContainer {
+    /// Starts building container configuration for a given `image`
+    ///
+    /// # Examples
+    /// ```
+    /// let config = docker_helper::CreateContainer::builder("postgres:15")
+    ///     .env("POSTGRES_PASSWORD", "secret")
+    ///     .cmd(["postgres", "-c", "fsync=off"])
+    ///     .network_mode("host")
+    ///     .build();
+    /// assert_eq!(config.host_config.network_mode.as_deref(), Some("host"));
+    /// ```
+    pub fn builder(image: &str) -> CreateContainerBuilder {
+        CreateContainerBuilder {
+            config: CreateContainer {
+                image: image.to_owned(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Builder of [CreateContainer]
+#[derive(Debug, Clone)]
+pub struct CreateContainerBuilder {
+    config: CreateContainer,
+}
+
+impl CreateContainerBuilder {
+    /// Command to run, overriding image `CMD`
+    pub fn cmd<I, S>(mut self, cmd: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.cmd = Some(cmd.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Entrypoint, overriding image `ENTRYPOINT`
+    pub fn entrypoint<I, S>(mut self, entrypoint: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.entrypoint = Some(entrypoint.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Adds environment variable
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.config
+            .env
+            .get_or_insert_with(Vec::new)
+            .push(format!("{}={}", key, value));
+        self
+    }
+
+    pub fn working_dir(mut self, working_dir: &str) -> Self {
+        self.config.working_dir = Some(working_dir.to_owned());
+        self
+    }
+
+    /// User in the form `user`, `user:group`, `uid` or `uid:gid`
+    pub fn user(mut self, user: &str) -> Self {
+        self.config.user = Some(user.to_owned());
+        self
+    }
+
+    /// Adds container label
+    pub fn label(mut self, key: &str, value: &str) -> Self {
+        self.config
+            .labels
+            .get_or_insert_with(HashMap::new)
+            .insert(key.to_owned(), value.to_owned());
+        self
+    }
+
+    pub fn hostname(mut self, hostname: &str) -> Self {
+        self.config.hostname = Some(hostname.to_owned());
+        self
+    }
+
+    pub fn domainname(mut self, domainname: &str) -> Self {
+        self.config.domainname = Some(domainname.to_owned());
+        self
+    }
+
+    /// Exposes container port in the form `port/protocol`, e.g. `8080/tcp`
+    pub fn expose(mut self, port: &str) -> Self {
+        self.config
+            .exposed_ports
+            .get_or_insert_with(HashMap::new)
+            .insert(port.to_owned(), EmptyObject {});
+        self
+    }
+
+    /// Signal sent to stop the container, e.g. `SIGINT`
+    pub fn stop_signal(mut self, signal: &str) -> Self {
+        self.config.stop_signal = Some(signal.to_owned());
+        self
+    }
+
+    /// Seconds to wait for the container to stop before killing it
+    pub fn stop_timeout(mut self, seconds: i64) -> Self {
+        self.config.stop_timeout = Some(seconds);
+        self
+    }
+
+    /// Allocates pseudo-TTY
+    pub fn tty(mut self, tty: bool) -> Self {
+        self.config.tty = Some(tty);
+        self
+    }
+
+    /// Keeps stdin open
+    pub fn open_stdin(mut self, open_stdin: bool) -> Self {
+        self.config.open_stdin = Some(open_stdin);
+        self
+    }
+
+    /// Healthcheck to run inside the container, see [crate::wait_for_healthy]
+    pub fn healthcheck(mut self, healthcheck: Healthcheck) -> Self {
+        self.config.healthcheck = Some(healthcheck);
+        self
+    }
+
+    /// Exposes and publishes container port to the host
+    pub fn publish(mut self, port: PortMapping) -> Self {
+        let key = port.key();
+        let binding = PortBinding {
+            host_ip: port.host_ip.unwrap_or_default(),
+            host_port: port.host_port.map(|p| p.to_string()).unwrap_or_default(),
+        };
+        self = self.expose(&key);
+        self.config
+            .host_config
+            .port_bindings
+            .get_or_insert_with(HashMap::new)
+            .entry(key)
+            .or_default()
+            .push(binding);
+        self
+    }
+
+    /// Publishes all exposed ports to random host ports
+    pub fn publish_all_ports(mut self, publish_all_ports: bool) -> Self {
+        self.config.host_config.publish_all_ports = Some(publish_all_ports);
+        self
+    }
+
+    /// Adds bind mount in `Binds` syntax
+    pub fn bind(mut self, bind: BindMount) -> Self {
+        self.config
+            .host_config
+            .binds
+            .get_or_insert_with(Vec::new)
+            .push(bind.to_string());
+        self
+    }
+
+    /// Adds bind, volume or tmpfs mount
+    pub fn mount(mut self, mount: Mount) -> Self {
+        self.config
+            .host_config
+            .mounts
+            .get_or_insert_with(Vec::new)
+            .push(mount);
+        self
+    }
+
+    /// Memory limit in bytes
+    pub fn memory(mut self, bytes: i64) -> Self {
+        self.config.host_config.resources.memory = Some(bytes);
+        self
+    }
+
+    /// Total memory plus swap limit in bytes, `-1` for unlimited swap
+    pub fn memory_swap(mut self, bytes: i64) -> Self {
+        self.config.host_config.resources.memory_swap = Some(bytes);
+        self
+    }
+
+    /// CPU quota in units of 10<sup>-9</sup> CPUs
+    pub fn nano_cpus(mut self, nano_cpus: i64) -> Self {
+        self.config.host_config.resources.nano_cpus = Some(nano_cpus);
+        self
+    }
+
+    /// CPU quota as a number of CPUs, e.g. `1.5`
+    pub fn cpus(self, cpus: f64) -> Self {
+        self.nano_cpus((cpus * 1e9) as i64)
+    }
+
+    /// Relative CPU weight
+    pub fn cpu_shares(mut self, shares: i64) -> Self {
+        self.config.host_config.resources.cpu_shares = Some(shares);
+        self
+    }
+
+    /// CPUs allowed to run the container, e.g. `0-3` or `0,1`
+    pub fn cpuset_cpus(mut self, cpus: &str) -> Self {
+        self.config.host_config.resources.cpuset_cpus = Some(cpus.to_owned());
+        self
+    }
+
+    /// Maximum number of processes, `-1` for unlimited
+    pub fn pids_limit(mut self, limit: i64) -> Self {
+        self.config.host_config.resources.pids_limit = Some(limit);
+        self
+    }
+
+    /// Size of `/dev/shm` in bytes
+    pub fn shm_size(mut self, bytes: i64) -> Self {
+        self.config.host_config.resources.shm_size = Some(bytes);
+        self
+    }
+
+    /// Adds ulimit, e.g. `nofile`
+    pub fn ulimit(mut self, name: &str, soft: i64, hard: i64) -> Self {
+        self.config
+            .host_config
+            .resources
+            .ulimits
+            .get_or_insert_with(Vec::new)
+            .push(Ulimit {
+                name: name.to_owned(),
+                soft,
+                hard,
+            });
+        self
+    }
+
+    /// Replaces all resource limits
+    pub fn resources(mut self, resources: ResourceLimits) -> Self {
+        self.config.host_config.resources = resources;
+        self
+    }
+
+    /// Adds kernel capability, e.g. `NET_ADMIN`
+    pub fn cap_add(mut self, capability: &str) -> Self {
+        self.config
+            .host_config
+            .cap_add
+            .get_or_insert_with(Vec::new)
+            .push(capability.to_owned());
+        self
+    }
+
+    /// Drops kernel capability, e.g. `ALL`
+    pub fn cap_drop(mut self, capability: &str) -> Self {
+        self.config
+            .host_config
+            .cap_drop
+            .get_or_insert_with(Vec::new)
+            .push(capability.to_owned());
+        self
+    }
+
+    pub fn privileged(mut self, privileged: bool) -> Self {
+        self.config.host_config.privileged = Some(privileged);
+        self
+    }
+
+    /// Mounts container root filesystem as read only
+    pub fn readonly_rootfs(mut self, readonly_rootfs: bool) -> Self {
+        self.config.host_config.readonly_rootfs = Some(readonly_rootfs);
+        self
+    }
+
+    /// Adds security option such as seccomp or AppArmor profile
+    pub fn security_opt(mut self, option: SecurityOption) -> Self {
+        self.config
+            .host_config
+            .security_opt
+            .get_or_insert_with(Vec::new)
+            .push(option.to_string());
+        self
+    }
+
+    /// User namespace mode, e.g. `host`
+    pub fn userns_mode(mut self, userns_mode: &str) -> Self {
+        self.config.host_config.userns_mode = Some(userns_mode.to_owned());
+        self
+    }
+
+    /// Runs an init process inside the container
+    pub fn init(mut self, init: bool) -> Self {
+        self.config.host_config.init = Some(init);
+        self
+    }
+
+    pub fn restart_policy(mut self, restart_policy: RestartPolicy) -> Self {
+        self.config.host_config.restart_policy = Some(restart_policy);
+        self
+    }
+
+    /// Removes the container once it exits, so that it does not outlive a crashed test
+    pub fn auto_remove(mut self, auto_remove: bool) -> Self {
+        self.config.host_config.auto_remove = Some(auto_remove);
+        self
+    }
+
+    /// Adds `/etc/hosts` entry
+    pub fn extra_host(mut self, host: &str, ip: &str) -> Self {
+        self.config
+            .host_config
+            .extra_hosts
+            .get_or_insert_with(Vec::new)
+            .push(format!("{}:{}", host, ip));
+        self
+    }
+
+    /// Adds `/etc/hosts` entry resolving `host` to the host machine, e.g. `host.docker.internal`
+    ///
+    /// # Examples
+    /// ```
+    /// let config = docker_helper::CreateContainer::builder("ubuntu:20.04")
+    ///     .host_gateway("host.docker.internal")
+    ///     .build();
+    /// assert_eq!(
+    ///     config.host_config.extra_hosts,
+    ///     Some(vec!["host.docker.internal:host-gateway".to_owned()])
+    /// );
+    /// ```
+    pub fn host_gateway(self, host: &str) -> Self {
+        self.extra_host(host, HOST_GATEWAY)
+    }
+
+    /// Adds DNS server
+    pub fn dns(mut self, server: &str) -> Self {
+        self.config
+            .host_config
+            .dns
+            .get_or_insert_with(Vec::new)
+            .push(server.to_owned());
+        self
+    }
+
+    /// Adds DNS search domain
+    pub fn dns_search(mut self, domain: &str) -> Self {
+        self.config
+            .host_config
+            .dns_search
+            .get_or_insert_with(Vec::new)
+            .push(domain.to_owned());
+        self
+    }
+
+    /// Adds `resolv.conf` option, e.g. `ndots:1`
+    pub fn dns_option(mut self, option: &str) -> Self {
+        self.config
+            .host_config
+            .dns_options
+            .get_or_insert_with(Vec::new)
+            .push(option.to_owned());
+        self
+    }
+
+    /// Network mode, e.g. `bridge`, `host` or `container:<name|id>`
+    pub fn network_mode(mut self, network_mode: &str) -> Self {
+        self.config.host_config.network_mode = Some(network_mode.to_owned());
+        self
+    }
+
+    /// Replaces the whole host configuration
+    pub fn host_config(mut self, host_config: HostConfig) -> Self {
+        self.config.host_config = host_config;
+        self
+    }
+
+    pub fn build(self) -> CreateContainer {
+        self.config
+    }
+}
@@ -0,0 +1,244 @@
+use crate::error::Result;
+use anyhow::Context;
+use regex::Regex;
+
+/// Parsed `.dockerignore` file, matching paths the same way Docker CLI does:
+/// patterns are matched against a path or any of its parent directories and
+/// the last matching pattern wins, `!` patterns re-including excluded paths.
+#[derive(Debug, Default)]
+pub(crate) struct DockerIgnore {
+    patterns: Vec<Pattern>,
+    /// Paths sent regardless of patterns, see [DockerIgnore::keep]
+    kept: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Pattern {
+    regex: Regex,
+    /// Number of path components, used to match parent directories
+    depth: usize,
+    exclusion: bool,
+}
+
+impl DockerIgnore {
+    pub(crate) fn parse(contents: &str) -> Result<DockerIgnore> {
+        let mut patterns = Vec::new();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (exclusion, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => (true, pattern.trim()),
+                None => (false, line),
+            };
+            let pattern = clean_path(pattern);
+            if pattern.is_empty() {
+                continue;
+            }
+            let regex = Regex::new(&to_regex(&pattern))
+                .with_context(|| format!("Invalid .dockerignore pattern: {}", line))?;
+            patterns.push(Pattern {
+                regex,
+                depth: pattern.split('/').count(),
+                exclusion,
+            });
+        }
+        Ok(DockerIgnore {
+            patterns,
+            kept: Vec::new(),
+        })
+    }
+
+    /// Re-includes a path regardless of patterns, Docker always sends
+    /// the Dockerfile and `.dockerignore` to the daemon
+    pub(crate) fn keep(&mut self, path: &str) {
+        self.kept.push(clean_path(path));
+    }
+
+    /// Whether anything below an ignored directory can still be included, i.e. whether
+    /// the directory has to be walked: there are `!` patterns or a kept path is inside
+    pub(crate) fn may_include_below(&self, dir: &str) -> bool {
+        self.patterns.iter().any(|pattern| pattern.exclusion)
+            || self.kept.iter().any(|path| {
+                path.strip_prefix(dir)
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+    }
+
+    /// Whether a `/` separated path relative to the build context is ignored
+    pub(crate) fn is_ignored(&self, path: &str) -> bool {
+        if self.kept.iter().any(|kept| kept == path) {
+            return false;
+        }
+        let components: Vec<&str> = path.split('/').collect();
+        let mut ignored = false;
+        for pattern in &self.patterns {
+            // Only a later exclusion can change the result for an already ignored path
+            if pattern.exclusion && !ignored {
+                continue;
+            }
+            let matched = pattern.regex.is_match(path)
+                || (pattern.depth < components.len()
+                    && pattern
+                        .regex
+                        .is_match(&components[..pattern.depth].join("/")));
+            if matched {
+                ignored = !pattern.exclusion;
+            }
+        }
+        ignored
+    }
+}
+
+/// Normalizes path the way Go `filepath.Clean` does, also dropping the leading `/`
+pub(crate) fn clean_path(path: &str) -> String {
+    let mut components: Vec<&str> = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+    components.join("/")
+}
+
+/// Converts Docker pattern into anchored regex: `*` and `?` do not cross `/`,
+/// `**` matches any number of directories
+fn to_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    let mut in_class = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '[' if !in_class => {
+                in_class = true;
+                regex.push(c);
+            }
+            ']' if in_class => {
+                in_class = false;
+                regex.push(c);
+            }
+            '-' | '^' if in_class => regex.push(c),
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                }
+                if chars.peek().is_none() {
+                    regex.push_str(".*");
+                } else {
+                    regex.push_str("(.*/)?");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    regex.push_str(&regex::escape(&escaped.to_string()));
+                }
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ignore(contents: &str) -> DockerIgnore {
+        DockerIgnore::parse(contents).unwrap()
+    }
+
+    #[test]
+    fn comments_blank_lines_and_leading_slash() {
+        let ignore = ignore("# target\n\n  /build/  \n./dist\n");
+        assert!(!ignore.is_ignored("target"));
+        assert!(ignore.is_ignored("build"));
+        assert!(ignore.is_ignored("dist"));
+        assert!(!ignore.is_ignored("src/build"));
+    }
+
+    #[test]
+    fn single_star_and_question_mark_stay_within_component() {
+        let ignore = ignore("*.log\nsrc/*.tmp\ncache?\n");
+        assert!(ignore.is_ignored("app.log"));
+        assert!(!ignore.is_ignored("logs/app.log"));
+        assert!(ignore.is_ignored("src/a.tmp"));
+        assert!(!ignore.is_ignored("src/nested/a.tmp"));
+        assert!(ignore.is_ignored("cache1"));
+        assert!(!ignore.is_ignored("cache"));
+        assert!(!ignore.is_ignored("cache12"));
+    }
+
+    #[test]
+    fn double_star_matches_any_number_of_directories() {
+        let ignore = ignore("**/*.log\ndocs/**\nsrc/**/generated\n");
+        assert!(ignore.is_ignored("app.log"));
+        assert!(ignore.is_ignored("a/b/c/app.log"));
+        assert!(ignore.is_ignored("docs/a/b.md"));
+        assert!(ignore.is_ignored("src/generated"));
+        assert!(ignore.is_ignored("src/a/b/generated"));
+        assert!(!ignore.is_ignored("lib/generated"));
+    }
+
+    #[test]
+    fn character_classes() {
+        let ignore = ignore("file[0-9].txt\nlog[^a].txt\n");
+        assert!(ignore.is_ignored("file7.txt"));
+        assert!(!ignore.is_ignored("filex.txt"));
+        assert!(ignore.is_ignored("logb.txt"));
+        assert!(!ignore.is_ignored("loga.txt"));
+    }
+
+    #[test]
+    fn escapes_and_regex_characters_are_literal() {
+        let ignore = ignore("\\*.txt\na+b(1).md\n");
+        assert!(ignore.is_ignored("*.txt"));
+        assert!(!ignore.is_ignored("a.txt"));
+        assert!(ignore.is_ignored("a+b(1).md"));
+        assert!(!ignore.is_ignored("aab1.md"));
+    }
+
+    #[test]
+    fn pattern_matching_parent_directory_ignores_contents() {
+        let ignore = ignore("node_modules\nsrc/*\n");
+        assert!(ignore.is_ignored("node_modules/a/index.js"));
+        assert!(ignore.is_ignored("src/main.rs"));
+        assert!(ignore.is_ignored("src/nested/mod.rs"));
+        assert!(!ignore.is_ignored("lib/node_modules"));
+    }
+
+    #[test]
+    fn last_matching_pattern_wins() {
+        let ignore = ignore("docs\n!docs/README.md\n**/*.md\n!keep.md\n");
+        assert!(ignore.is_ignored("docs/guide.txt"));
+        assert!(ignore.is_ignored("docs/README.md"));
+        assert!(ignore.is_ignored("CHANGELOG.md"));
+        assert!(!ignore.is_ignored("keep.md"));
+
+        let ignore = DockerIgnore::parse("docs\n!docs/README.md\n").unwrap();
+        assert!(!ignore.is_ignored("docs/README.md"));
+        assert!(ignore.may_include_below("docs"));
+    }
+
+    #[test]
+    fn kept_paths_are_never_ignored() {
+        let mut ignore = ignore("*\n");
+        ignore.keep("./docker/Dockerfile");
+        ignore.keep(".dockerignore");
+        assert!(!ignore.is_ignored("docker/Dockerfile"));
+        assert!(!ignore.is_ignored(".dockerignore"));
+        assert!(ignore.is_ignored("docker"));
+        assert!(ignore.is_ignored("docker/other"));
+        assert!(ignore.may_include_below("docker"));
+        assert!(!ignore.may_include_below("dock"));
+        assert!(!ignore.may_include_below("target"));
+    }
+}
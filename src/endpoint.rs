@@ -0,0 +1,342 @@
+use crate::config::{config_dir, DockerConfig};
+use crate::error::Result;
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Default location of the Docker daemon unix socket
+pub const DEFAULT_SOCKET: &str = "/var/run/docker.sock";
+
+/// Address of a Docker daemon
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    /// Unix domain socket at a given path
+    Unix(PathBuf),
+    /// Plain TCP address in the form `host:port`
+    Tcp(String),
+    /// TCP address in the form `host:port` secured with TLS
+    Tls { address: String, config: TlsConfig },
+}
+
+/// Certificates used to talk to a TLS protected daemon
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// CA certificate used to verify the daemon (`ca.pem`)
+    pub ca: Option<PathBuf>,
+    /// Client certificate (`cert.pem`)
+    pub cert: Option<PathBuf>,
+    /// Client private key (`key.pem`)
+    pub key: Option<PathBuf>,
+    /// Whether daemon certificate and host name are verified
+    pub verify: bool,
+}
+
+impl TlsConfig {
+    /// Picks up `ca.pem`, `cert.pem` and `key.pem` present in a given directory
+    ///
+    /// # Arguments
+    /// * `dir` - directory with certificates, e.g. `DOCKER_CERT_PATH`
+    /// * `verify` - whether daemon certificate should be verified
+    pub fn from_dir(dir: &Path, verify: bool) -> TlsConfig {
+        let existing = |name: &str| Some(dir.join(name)).filter(|path| path.is_file());
+        TlsConfig {
+            ca: existing("ca.pem"),
+            cert: existing("cert.pem"),
+            key: existing("key.pem"),
+            verify,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ca.is_none() && self.cert.is_none() && self.key.is_none()
+    }
+}
+
+impl Default for Endpoint {
+    fn default() -> Self {
+        Endpoint::Unix(PathBuf::from(DEFAULT_SOCKET))
+    }
+}
+
+impl Endpoint {
+    /// Parses Docker host string such as `unix:///var/run/docker.sock` or `tcp://127.0.0.1:2375`
+    ///
+    /// # Examples
+    /// ```
+    /// use docker_helper::Endpoint;
+    ///
+    /// let endpoint = Endpoint::parse("tcp://127.0.0.1:2375").unwrap();
+    /// assert_eq!(endpoint, Endpoint::Tcp("127.0.0.1:2375".to_owned()));
+    /// ```
+    pub fn parse(host: &str) -> Result<Endpoint> {
+        if let Some(path) = host.strip_prefix("unix://") {
+            if path.is_empty() {
+                return Ok(Endpoint::default());
+            }
+            Ok(Endpoint::Unix(PathBuf::from(path)))
+        } else if host.starts_with("tcp://") {
+            Ok(Endpoint::Tcp(tcp_address(host, 2375)?))
+        } else {
+            Err(anyhow!("Unsupported Docker host: {}", host).into())
+        }
+    }
+
+    /// Parses Docker host string and secures TCP connection with a given TLS config.
+    /// Port defaults to `2376` for TLS connections.
+    ///
+    /// # Examples
+    /// ```
+    /// use docker_helper::{Endpoint, TlsConfig};
+    ///
+    /// let endpoint = Endpoint::parse_with_tls("tcp://build-host", TlsConfig::default()).unwrap();
+    /// assert!(matches!(endpoint, Endpoint::Tls { address, .. } if address == "build-host:2376"));
+    /// ```
+    pub fn parse_with_tls(host: &str, config: TlsConfig) -> Result<Endpoint> {
+        if host.starts_with("tcp://") {
+            Ok(Endpoint::Tls {
+                address: tcp_address(host, 2376)?,
+                config,
+            })
+        } else {
+            Endpoint::parse(host)
+        }
+    }
+
+    /// Resolves endpoint the same way Docker CLI does: `DOCKER_HOST` environment variable,
+    /// then Docker context named by `DOCKER_CONTEXT` or `currentContext` of `config.json`,
+    /// falling back to [DEFAULT_SOCKET].
+    ///
+    /// TCP connections to `DOCKER_HOST` use TLS when `DOCKER_TLS_VERIFY` (verifying the daemon)
+    /// or `DOCKER_TLS` is set, with certificates taken from `DOCKER_CERT_PATH` or `~/.docker`.
+    pub fn from_env() -> Result<Endpoint> {
+        if let Some(host) = non_empty_var("DOCKER_HOST") {
+            let verify = non_empty_var("DOCKER_TLS_VERIFY").is_some();
+            if !verify && non_empty_var("DOCKER_TLS").is_none() {
+                return Endpoint::parse(&host);
+            }
+            let cert_path = non_empty_var("DOCKER_CERT_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(config_dir);
+            return Endpoint::parse_with_tls(&host, TlsConfig::from_dir(&cert_path, verify));
+        }
+        let context = match non_empty_var("DOCKER_CONTEXT") {
+            Some(context) => Some(context),
+            None => DockerConfig::load()?.current_context,
+        };
+        match context {
+            Some(name) => Endpoint::from_context(&name),
+            None => Ok(Endpoint::default()),
+        }
+    }
+
+    /// Resolves endpoint of a Docker context stored in `~/.docker/contexts/meta`
+    ///
+    /// # Arguments
+    /// * `name` - context name as shown by `docker context ls`
+    ///
+    /// # Examples
+    /// ```no_run
+    /// let endpoint = docker_helper::Endpoint::from_context("rootless");
+    /// ```
+    pub fn from_context(name: &str) -> Result<Endpoint> {
+        if name.is_empty() || name == "default" {
+            return Ok(Endpoint::default());
+        }
+        let path = context_dir("meta", name).join("meta.json");
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("Docker context {} not found at {}", name, path.display()))?;
+        let metadata: ContextMetadata = serde_json::from_str(&data)
+            .with_context(|| format!("Failed to parse Docker context metadata: {}", data))?;
+        let endpoint = metadata
+            .endpoints
+            .get("docker")
+            .context(format!("Docker context {} has no docker endpoint", name))?;
+        let host = endpoint
+            .host
+            .as_deref()
+            .context(format!("Docker context {} has no docker host", name))?;
+
+        let tls = TlsConfig::from_dir(
+            &context_dir("tls", name).join("docker"),
+            !endpoint.skip_tls_verify,
+        );
+        if tls.is_empty() && !endpoint.skip_tls_verify {
+            Endpoint::parse(host)
+        } else {
+            Endpoint::parse_with_tls(host, tls)
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ContextMetadata {
+    #[serde(rename = "Endpoints", default)]
+    endpoints: HashMap<String, ContextEndpoint>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ContextEndpoint {
+    #[serde(rename = "Host")]
+    host: Option<String>,
+    #[serde(rename = "SkipTLSVerify", default)]
+    skip_tls_verify: bool,
+}
+
+/// Extracts `host:port` from `tcp://` Docker host string, appending `default_port` if missing
+fn tcp_address(host: &str, default_port: u16) -> Result<String> {
+    let address = host
+        .strip_prefix("tcp://")
+        .unwrap_or(host)
+        .trim_end_matches('/');
+    if address.is_empty() {
+        return Err(anyhow!("Docker host ({}) has no address", host).into());
+    }
+    let port_part = address.rsplit_once(']').map_or(address, |(_, rest)| rest);
+    if port_part.contains(':') {
+        Ok(address.to_owned())
+    } else {
+        Ok(format!("{}:{}", address, default_port))
+    }
+}
+
+/// Context storage directory, named after SHA-256 digest of context name
+fn context_dir(kind: &str, name: &str) -> PathBuf {
+    let digest = Sha256::digest(name.as_bytes());
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    config_dir().join("contexts").join(kind).join(hex)
+}
+
+fn non_empty_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{lock_env, TempDir};
+
+    fn write_certs(dir: &Path) {
+        fs::create_dir_all(dir).unwrap();
+        for name in ["ca.pem", "cert.pem", "key.pem"] {
+            fs::write(dir.join(name), "pem").unwrap();
+        }
+    }
+
+    #[test]
+    fn from_env_without_tls_uses_plain_tcp() {
+        let _env = lock_env();
+        env::set_var("DOCKER_HOST", "tcp://build-host:2375");
+        assert_eq!(
+            Endpoint::from_env().unwrap(),
+            Endpoint::Tcp("build-host:2375".to_owned())
+        );
+    }
+
+    #[test]
+    fn from_env_tls_verify_uses_cert_path() {
+        let _env = lock_env();
+        let certs = TempDir::new();
+        write_certs(certs.path());
+        env::set_var("DOCKER_HOST", "tcp://build-host");
+        env::set_var("DOCKER_TLS_VERIFY", "1");
+        env::set_var("DOCKER_CERT_PATH", certs.path());
+
+        let expected = Endpoint::Tls {
+            address: "build-host:2376".to_owned(),
+            config: TlsConfig {
+                ca: Some(certs.path().join("ca.pem")),
+                cert: Some(certs.path().join("cert.pem")),
+                key: Some(certs.path().join("key.pem")),
+                verify: true,
+            },
+        };
+        assert_eq!(Endpoint::from_env().unwrap(), expected);
+    }
+
+    #[test]
+    fn from_env_tls_without_verify_defaults_to_config_dir() {
+        let _env = lock_env();
+        let config = TempDir::new();
+        config.write("cert.pem", "pem");
+        config.write("key.pem", "pem");
+        env::set_var("DOCKER_HOST", "tcp://build-host:3000");
+        env::set_var("DOCKER_TLS", "1");
+        env::set_var("DOCKER_CONFIG", config.path());
+
+        let expected = Endpoint::Tls {
+            address: "build-host:3000".to_owned(),
+            config: TlsConfig {
+                ca: None,
+                cert: Some(config.path().join("cert.pem")),
+                key: Some(config.path().join("key.pem")),
+                verify: false,
+            },
+        };
+        assert_eq!(Endpoint::from_env().unwrap(), expected);
+    }
+
+    /// Writes context metadata under `DOCKER_CONFIG`
+    fn write_context(name: &str, host: &str, skip_tls_verify: bool) {
+        let meta = context_dir("meta", name).join("meta.json");
+        fs::create_dir_all(meta.parent().unwrap()).unwrap();
+        let data = serde_json::json!({
+            "Name": name,
+            "Endpoints": {"docker": {"Host": host, "SkipTLSVerify": skip_tls_verify}},
+        });
+        fs::write(meta, data.to_string()).unwrap();
+    }
+
+    #[test]
+    fn from_context_with_tls_files_verifies_daemon() {
+        let _env = lock_env();
+        let config = TempDir::new();
+        env::set_var("DOCKER_CONFIG", config.path());
+        write_context("remote", "tcp://remote-host:2376", false);
+        let tls_dir = context_dir("tls", "remote").join("docker");
+        write_certs(&tls_dir);
+
+        let expected = Endpoint::Tls {
+            address: "remote-host:2376".to_owned(),
+            config: TlsConfig {
+                ca: Some(tls_dir.join("ca.pem")),
+                cert: Some(tls_dir.join("cert.pem")),
+                key: Some(tls_dir.join("key.pem")),
+                verify: true,
+            },
+        };
+        assert_eq!(Endpoint::from_context("remote").unwrap(), expected);
+        env::set_var("DOCKER_CONTEXT", "remote");
+        assert_eq!(Endpoint::from_env().unwrap(), expected);
+    }
+
+    #[test]
+    fn from_context_skip_tls_verify_uses_tls_without_verification() {
+        let _env = lock_env();
+        let config = TempDir::new();
+        env::set_var("DOCKER_CONFIG", config.path());
+        write_context("insecure", "tcp://remote-host", true);
+
+        let expected = Endpoint::Tls {
+            address: "remote-host:2376".to_owned(),
+            config: TlsConfig::default(),
+        };
+        assert_eq!(Endpoint::from_context("insecure").unwrap(), expected);
+    }
+
+    #[test]
+    fn from_context_without_tls_uses_plain_tcp() {
+        let _env = lock_env();
+        let config = TempDir::new();
+        env::set_var("DOCKER_CONFIG", config.path());
+        write_context("plain", "tcp://remote-host:2375", false);
+
+        assert_eq!(
+            Endpoint::from_context("plain").unwrap(),
+            Endpoint::Tcp("remote-host:2375".to_owned())
+        );
+    }
+}
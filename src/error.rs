@@ -0,0 +1,141 @@
+use serde::Deserialize;
+use std::fmt;
+
+/// Result type returned by all Docker operations
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Error returned by Docker operations
+///
+/// # Examples
+/// ```no_run
+/// match docker_helper::stop_container("6fe66725ed81") {
+///     Err(e) if e.is_not_modified() => println!("container is already stopped"),
+///     Err(e) if e.is_not_found() => println!("no such container"),
+///     result => result.unwrap(),
+/// }
+/// ```
+#[derive(Debug)]
+pub enum Error {
+    /// Docker daemon responded with a non-success HTTP status
+    Api {
+        /// Called endpoint including query string, e.g. `/containers/6fe66725ed81/stop`
+        endpoint: String,
+        status: u32,
+        /// `message` reported by the daemon or the raw response body
+        message: String,
+    },
+    /// Docker daemon reported a failure inside a streamed response, e.g. image pull progress
+    Stream { endpoint: String, message: String },
+    /// Any other failure: transport, configuration or (de)serialization
+    Other(anyhow::Error),
+}
+
+impl Error {
+    /// Builds [Error::Api] from a raw daemon response body
+    pub(crate) fn api(endpoint: String, status: u32, body: &str) -> Error {
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            message: String,
+        }
+
+        let message = match serde_json::from_str::<ErrorBody>(body) {
+            Ok(parsed) => parsed.message,
+            Err(_) => body.trim().to_owned(),
+        };
+        Error::Api {
+            endpoint,
+            status,
+            message,
+        }
+    }
+
+    /// HTTP status returned by the daemon, if any
+    pub fn status(&self) -> Option<u32> {
+        match self {
+            Error::Api { status, .. } => Some(*status),
+            Error::Stream { .. } | Error::Other(_) => None,
+        }
+    }
+
+    /// Message reported by the daemon, if any
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            Error::Api { message, .. } | Error::Stream { message, .. } => Some(message),
+            Error::Other(_) => None,
+        }
+    }
+
+    /// `304 Not Modified`, e.g. container is already started or stopped
+    pub fn is_not_modified(&self) -> bool {
+        self.status() == Some(304)
+    }
+
+    /// `404 Not Found`, e.g. no such container or image
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(404)
+    }
+
+    /// `409 Conflict`, e.g. container name is already in use
+    pub fn is_conflict(&self) -> bool {
+        self.status() == Some(409)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Api {
+                endpoint,
+                status,
+                message,
+            } => write!(
+                f,
+                "Docker API call ({}) failed with status {}: {}",
+                endpoint, status, message
+            ),
+            Error::Stream { endpoint, message } => {
+                write!(f, "Docker API call ({}) failed: {}", endpoint, message)
+            }
+            Error::Other(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Api { .. } | Error::Stream { .. } => None,
+            Error::Other(e) => e.source(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        Error::Other(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Other(e.into())
+    }
+}
+
+impl From<curl::Error> for Error {
+    fn from(e: curl::Error) -> Self {
+        Error::Other(e.into())
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Other(e.into())
+    }
+}
+
+impl From<std::string::FromUtf8Error> for Error {
+    fn from(e: std::string::FromUtf8Error) -> Self {
+        Error::Other(e.into())
+    }
+}
@@ -1,25 +1,45 @@
-//! This crate contains a set of utilities that use [curl::easy::Easy] to interact with
-//! Docker unix socket located at `/var/run/docker.sock` in order to perform certain Docker
-//! operations. It can be useful in writing tests which have external service dependencies
-//! that need to be orchestrated from within rust.
+//! This crate contains a set of utilities that use [curl::easy::Easy] (or any other
+//! [Transport]) to interact with Docker daemon in order to perform certain Docker operations.
+//! It can be useful in writing tests which have external service dependencies that need to be
+//! orchestrated from within rust.
+//!
+//! Free functions talk to the daemon configured through `DOCKER_HOST` environment variable
+//! or the current Docker context, falling back to unix socket located at
+//! `/var/run/docker.sock`. Use [DockerClient] to target a specific [Endpoint].
 
+mod auth;
+mod build;
+mod client;
+mod config;
+mod container;
+mod dockerignore;
+mod endpoint;
+mod error;
+mod reference;
+#[cfg(test)]
+mod test_support;
+mod transport;
 mod types;
 
+pub use crate::auth::*;
+pub use crate::build::*;
+pub use crate::client::*;
+pub use crate::container::*;
+pub use crate::endpoint::*;
+pub use crate::error::*;
+pub use crate::reference::*;
+pub use crate::transport::*;
 pub use crate::types::*;
-use anyhow::{anyhow, Context, Result};
-use curl::easy::{Easy, List};
-use serde_json::ser::to_string;
-use std::io::Read;
-use urlencoding::encode;
+use std::collections::HashMap;
+use std::time::Duration;
 
-/// High level utility that pulls image, creates container with a given image,
-/// maps container port to host one and automatically starts it.
+/// High level utility that pulls image, creates container with a given image
+/// attached to a given network and automatically starts it.
 ///
 /// # Arguments
 /// * `container_name` - Unique container name
 /// * `image` - Full name of Docker image in the form `image:version`
-/// * `container_port` - internal container port to forward to host
-/// * `host_port` - host port to forward internal container port to
+/// * `network_mode` - network mode, e.g. `bridge` or `host`
 ///
 /// # Examples
 /// ```no_run
@@ -30,38 +50,216 @@ pub fn start_container_with_network_mode(
     image: &str,
     network_mode: &str,
 ) -> Result<String> {
-    let existing_images = find_images(image)?;
-    if existing_images.is_empty() {
-        pull_image(image)?;
-    }
+    DockerClient::from_env()?.start_container_with_network_mode(container_name, image, network_mode)
+}
 
-    let id = create_container(
-        container_name,
-        CreateContainer {
-            image: image.to_owned(),
-            network_mode: network_mode.to_owned(),
-        },
-    )?;
-    start_container(&id)?;
-    Ok(id)
+/// High level utility that pulls image, creates container with a given image,
+/// publishes container ports to host ones and automatically starts it.
+///
+/// # Arguments
+/// * `container_name` - Unique container name
+/// * `image` - Full name of Docker image in the form `image:version`
+/// * `ports` - container ports to publish, with fixed or daemon assigned host ports
+///
+/// # Examples
+/// ```no_run
+/// use docker_helper::PortMapping;
+///
+/// let result = docker_helper::start_container_with_ports(
+///     "test",
+///     "postgres:15",
+///     &[PortMapping::tcp(5432).host_port(15432).host_ip("127.0.0.1")],
+/// );
+/// ```
+pub fn start_container_with_ports(
+    container_name: &str,
+    image: &str,
+    ports: &[PortMapping],
+) -> Result<String> {
+    DockerClient::from_env()?.start_container_with_ports(container_name, image, ports)
 }
 
-/// Pulls Docker image
+/// High level utility that pulls image if it is missing, creates container with
+/// a given configuration and automatically starts it. Use
+/// [CreateContainerBuilder::auto_remove] so that the container is removed once stopped
+/// even if the test process crashes before cleaning it up.
 ///
 /// # Arguments
-/// * `image_name` - Full name of Docker image in the form `image:version`
+/// * `container_name` - Unique container name
+/// * `config` - container configuration, see [CreateContainer::builder]
+///
+/// # Examples
+/// ```no_run
+/// use docker_helper::CreateContainer;
+///
+/// let config = CreateContainer::builder("ubuntu:20.04")
+///     .cmd(["sleep", "infinity"])
+///     .env("TZ", "UTC")
+///     .auto_remove(true)
+///     .build();
+/// let result = docker_helper::start_container_with_config("test", config);
+/// ```
+pub fn start_container_with_config(
+    container_name: &str,
+    config: CreateContainer,
+) -> Result<String> {
+    DockerClient::from_env()?.start_container_with_config(container_name, config)
+}
+
+/// Creates container with a given configuration without starting it.
+/// Limits set by [set_default_resource_limits] apply unless overridden by `config`.
+///
+/// # Arguments
+/// * `container_name` - Unique container name
+/// * `config` - container configuration, see [CreateContainer::builder]
+///
+/// # Examples
+/// ```no_run
+/// let config = docker_helper::CreateContainer::builder("ubuntu:20.04").build();
+/// let result = docker_helper::create_container("test", config);
+/// ```
+pub fn create_container(container_name: &str, config: CreateContainer) -> Result<String> {
+    DockerClient::from_env()?.create_container(container_name, config)
+}
+
+/// Pulls Docker image. Registry credentials are resolved from Docker `config.json` by
+/// [RegistryAuth::from_config]: `credHelpers` or `credsStore` first, then `auths`.
+/// Failures reported by the daemon in the middle of the pull, e.g. unknown manifest,
+/// are returned as [Error::Stream].
+///
+/// # Arguments
+/// * `image_name` - Image reference in the form `[registry/]image[:version][@digest]`, see [ImageReference]
 ///
 /// # Examples
 /// ```no_run
 /// let result = docker_helper::pull_image("ubuntu:20.04");
 /// ```
 pub fn pull_image(image_name: &str) -> Result<()> {
-    let path = format!("/images/create?fromImage={}", image_name);
-    let _ = send_request(&path, true, false, None)?;
-    Ok(())
+    DockerClient::from_env()?.pull_image(image_name)
+}
+
+/// Pulls Docker image reporting each progress message sent by the daemon
+///
+/// # Arguments
+/// * `image_name` - Image reference in the form `[registry/]image[:version][@digest]`, see [ImageReference]
+/// * `on_progress` - callback invoked for every [ProgressEvent]
+///
+/// # Examples
+/// ```no_run
+/// let result = docker_helper::pull_image_with_progress("ubuntu:20.04", |event| {
+///     if let (Some(id), Some(detail)) = (&event.id, &event.progress_detail) {
+///         println!("{}: {:?}/{:?}", id, detail.current, detail.total);
+///     }
+/// });
+/// ```
+pub fn pull_image_with_progress(
+    image_name: &str,
+    on_progress: impl FnMut(&ProgressEvent),
+) -> Result<()> {
+    DockerClient::from_env()?.pull_image_with_progress(image_name, on_progress)
+}
+
+/// Tags image under a new repository and tag
+///
+/// # Arguments
+/// * `source` - image reference (`image_name:version`) or ID to tag
+/// * `repo` - target repository, e.g. `localhost:5000/team/app`
+/// * `tag` - target tag
+///
+/// # Examples
+/// ```no_run
+/// let result = docker_helper::tag_image("test-fixture:latest", "localhost:5000/test-fixture", "1.0");
+/// ```
+pub fn tag_image(source: &str, repo: &str, tag: &str) -> Result<()> {
+    DockerClient::from_env()?.tag_image(source, repo, tag)
+}
+
+/// Pushes Docker image to its registry. Registry credentials are resolved the same way
+/// as for [pull_image]. Failures reported by the daemon in the middle of the push,
+/// e.g. denied access, are returned as [Error::Stream]. Images cannot be pushed by digest.
+///
+/// # Arguments
+/// * `image_name` - Image reference in the form `[registry/]image[:version]`, see [ImageReference]
+///
+/// # Examples
+/// ```no_run
+/// docker_helper::tag_image("test-fixture:latest", "localhost:5000/test-fixture", "1.0").unwrap();
+/// let result = docker_helper::push_image("localhost:5000/test-fixture:1.0");
+/// ```
+pub fn push_image(image_name: &str) -> Result<()> {
+    DockerClient::from_env()?.push_image(image_name)
+}
+
+/// Pushes Docker image reporting each progress message sent by the daemon
+///
+/// # Arguments
+/// * `image_name` - Image reference in the form `[registry/]image[:version]`, see [ImageReference]
+/// * `on_progress` - callback invoked for every [ProgressEvent]
+///
+/// # Examples
+/// ```no_run
+/// let result = docker_helper::push_image_with_progress("localhost:5000/test-fixture:1.0", |event| {
+///     if let Some(status) = &event.status {
+///         println!("{}", status);
+///     }
+/// });
+/// ```
+pub fn push_image_with_progress(
+    image_name: &str,
+    on_progress: impl FnMut(&ProgressEvent),
+) -> Result<()> {
+    DockerClient::from_env()?.push_image_with_progress(image_name, on_progress)
+}
+
+/// Builds Docker image from a context directory or in-memory files, returning its ID.
+/// Build failures, e.g. a failing `RUN` instruction, are returned as [Error::Stream].
+/// With [BuildImageOptions::cache_by_content_hash] the build is skipped if an image with
+/// the same [BuildImageOptions::content_hash] already exists, which is then tagged instead,
+/// unless [BuildImageOptions::no_cache] or [BuildImageOptions::pull] asks for a fresh build.
+///
+/// # Arguments
+/// * `options` - build context, Dockerfile, tags and other build parameters
+///
+/// # Examples
+/// ```no_run
+/// use docker_helper::BuildImageOptions;
+///
+/// let options = BuildImageOptions::from_directory("tests/fixtures/app")
+///     .tag("test-app:latest")
+///     .cache_by_content_hash(true);
+/// let image_id = docker_helper::build_image(&options).unwrap();
+/// ```
+pub fn build_image(options: &BuildImageOptions) -> Result<String> {
+    DockerClient::from_env()?.build_image(options)
+}
+
+/// Builds Docker image reporting each progress message sent by the daemon
+///
+/// # Arguments
+/// * `options` - build context, Dockerfile, tags and other build parameters
+/// * `on_progress` - callback invoked for every [ProgressEvent]
+///
+/// # Examples
+/// ```no_run
+/// use docker_helper::BuildImageOptions;
+///
+/// let options = BuildImageOptions::from_dockerfile("FROM alpine:3.17\nRUN apk add curl\n");
+/// let result = docker_helper::build_image_with_progress(&options, |event| {
+///     if let Some(stream) = &event.stream {
+///         print!("{}", stream);
+///     }
+/// });
+/// ```
+pub fn build_image_with_progress(
+    options: &BuildImageOptions,
+    on_progress: impl FnMut(&ProgressEvent),
+) -> Result<String> {
+    DockerClient::from_env()?.build_image_with_progress(options, on_progress)
 }
 
-/// Stops and deletes container with a given `id`
+/// Stops and deletes container with a given `id`. Containers that are already stopped
+/// or removed by the daemon because of [CreateContainerBuilder::auto_remove] are not
+/// treated as an error.
 ///
 /// # Arguments
 /// * `id` - container id
@@ -72,8 +270,7 @@ pub fn pull_image(image_name: &str) -> Result<()> {
 /// let result = docker_helper::stop_and_cleanup_container(&id);
 /// ```
 pub fn stop_and_cleanup_container(id: &str) -> Result<()> {
-    stop_container(id)?;
-    delete_container(id)
+    DockerClient::from_env()?.stop_and_cleanup_container(id)
 }
 
 /// Starts container with a given `id`
@@ -86,9 +283,7 @@ pub fn stop_and_cleanup_container(id: &str) -> Result<()> {
 /// let result = docker_helper::start_container("6fe66725ed81");
 /// ```
 pub fn start_container(id: &str) -> Result<()> {
-    let path = format!("/containers/{}/start", id);
-    let _ = send_request(&path, true, false, None)?;
-    Ok(())
+    DockerClient::from_env()?.start_container(id)
 }
 
 /// Stops container with a given `id`
@@ -101,9 +296,7 @@ pub fn start_container(id: &str) -> Result<()> {
 /// let result = docker_helper::stop_container("6fe66725ed81");
 /// ```
 pub fn stop_container(id: &str) -> Result<()> {
-    let path = format!("/containers/{}/stop", id);
-    let _ = send_request(&path, true, false, None)?;
-    Ok(())
+    DockerClient::from_env()?.stop_container(id)
 }
 
 /// Deletes container with a given `id`
@@ -116,9 +309,7 @@ pub fn stop_container(id: &str) -> Result<()> {
 /// let result = docker_helper::delete_container("6fe66725ed81");
 /// ```
 pub fn delete_container(id: &str) -> Result<()> {
-    let path = format!("/containers/{}", id);
-    let _ = send_request(&path, false, true, None)?;
-    Ok(())
+    DockerClient::from_env()?.delete_container(id)
 }
 
 /// Prunes all stopped container
@@ -128,9 +319,7 @@ pub fn delete_container(id: &str) -> Result<()> {
 /// let result = docker_helper::prune_containers();
 /// ```
 pub fn prune_containers() -> Result<()> {
-    let path = "/containers/prune".to_string();
-    let _ = send_request(&path, true, false, None);
-    Ok(())
+    DockerClient::from_env()?.prune_containers()
 }
 
 /// Gets container IP from first network is the list
@@ -140,33 +329,119 @@ pub fn prune_containers() -> Result<()> {
 /// let result = docker_helper::get_container_ip("6fe66725ed81");
 /// ```
 pub fn get_container_ip(id: &str) -> Result<String> {
-    Ok(find_containers(id)?
-        .first()
-        .context(format!("No containers found with ID = {}", id))?
-        .network_settings
-        .networks
-        .values()
-        .next()
-        .context(format!("No network found for container with ID = {}", id))?
-        .ip_address
-        .to_owned())
+    DockerClient::from_env()?.get_container_ip(id)
+}
+
+/// Waits until container healthcheck reports `healthy`. Fails with the last healthcheck
+/// output if the container becomes `unhealthy`, stops, or `timeout` elapses.
+///
+/// # Arguments
+/// * `id` - container id
+/// * `timeout` - maximum time to wait
+///
+/// # Examples
+/// ```no_run
+/// use docker_helper::{CreateContainer, Healthcheck};
+/// use std::time::Duration;
+///
+/// let config = CreateContainer::builder("postgres:15")
+///     .env("POSTGRES_PASSWORD", "secret")
+///     .healthcheck(Healthcheck::shell("pg_isready -U postgres").interval(Duration::from_secs(1)))
+///     .build();
+/// let id = docker_helper::start_container_with_config("test", config).unwrap();
+/// let result = docker_helper::wait_for_healthy(&id, Duration::from_secs(60));
+/// ```
+pub fn wait_for_healthy(id: &str, timeout: Duration) -> Result<()> {
+    DockerClient::from_env()?.wait_for_healthy(id, timeout)
+}
+
+/// Gets address a container should use to reach services listening on the host:
+/// `127.0.0.1` for host networking, otherwise gateway of the first container network.
+/// Services have to listen on that address (or on all interfaces) to be reachable.
+///
+/// Alternatively, add [CreateContainerBuilder::host_gateway] entry and refer to the
+/// host by name.
+///
+/// # Examples
+/// ```no_run
+/// let result = docker_helper::get_host_address("6fe66725ed81");
+/// ```
+pub fn get_host_address(id: &str) -> Result<String> {
+    DockerClient::from_env()?.get_host_address(id)
+}
+
+/// Gets host port a given container port is published to
+///
+/// # Arguments
+/// * `id` - container id
+/// * `container_port` - internal container port
+/// * `protocol` - container port protocol
+///
+/// # Examples
+/// ```no_run
+/// use docker_helper::Protocol;
+///
+/// let result = docker_helper::get_host_port("6fe66725ed81", 5432, Protocol::Tcp);
+/// ```
+pub fn get_host_port(id: &str, container_port: u16, protocol: Protocol) -> Result<u16> {
+    DockerClient::from_env()?.get_host_port(id, container_port, protocol)
+}
+
+/// Gets all published ports of a container keyed by `port/protocol`
+///
+/// # Examples
+/// ```no_run
+/// let result = docker_helper::get_port_map("6fe66725ed81");
+/// ```
+pub fn get_port_map(id: &str) -> Result<HashMap<String, Vec<PortBinding>>> {
+    DockerClient::from_env()?.get_port_map(id)
+}
+
+/// Inspects container with a given ID returning its state, configuration, mounts and
+/// network settings. Fields without a typed model are kept as raw JSON in `extra`.
+///
+/// # Arguments
+/// * `id` - container id or name
+///
+/// # Examples
+/// ```no_run
+/// let details = docker_helper::inspect_container("6fe66725ed81").unwrap();
+/// if details.state.oom_killed {
+///     println!("{} ran out of memory", details.name);
+/// }
+/// let log_path = details.extra.get("LogPath");
+/// ```
+pub fn inspect_container(id: &str) -> Result<ContainerDetails> {
+    DockerClient::from_env()?.inspect_container(id)
 }
 
-/// Finds containers with a given ID
+/// Finds running containers with a given ID
 ///
 /// # Examples
 /// ```no_run
 /// let result = docker_helper::find_containers("6fe66725ed81");
 /// ```
 pub fn find_containers(id: &str) -> Result<Vec<ContainerDescriptor>> {
-    let filter = to_string(&ContainerFilter {
-        id: vec![id.to_owned()],
-    })?;
-    let path = format!("/containers/json?filters={}", encode(&filter));
-    let resp = send_request(&path, false, false, None)?;
-    let result: Vec<ContainerDescriptor> = serde_json::from_str(&resp)
-        .with_context(|| format!("Failed to parse find_images response json: {}", resp))?;
-    Ok(result)
+    DockerClient::from_env()?.find_containers(id)
+}
+
+/// Lists containers matching given options. Only running containers are listed unless
+/// [ListContainersOptions::all] is set.
+///
+/// # Arguments
+/// * `options` - `all`, `limit` and `size` flags along with container filters
+///
+/// # Examples
+/// ```no_run
+/// use docker_helper::ListContainersOptions;
+///
+/// let options = ListContainersOptions::new().all(true).ancestor("postgres:15").exited(1);
+/// for container in docker_helper::list_containers(&options).unwrap() {
+///     println!("{:?} {}", container.names, container.status);
+/// }
+/// ```
+pub fn list_containers(options: &ListContainersOptions) -> Result<Vec<ContainerDescriptor>> {
+    DockerClient::from_env()?.list_containers(options)
 }
 
 /// Finds images for a given reference string (`image_name:version`)
@@ -176,76 +451,55 @@ pub fn find_containers(id: &str) -> Result<Vec<ContainerDescriptor>> {
 /// let result = docker_helper::find_images("ubuntu:20.04");
 /// ```
 pub fn find_images(reference: &str) -> Result<Vec<ImageDescriptor>> {
-    let filter = to_string(&ImageFilter {
-        reference: vec![reference.to_owned()],
-    })?;
-    let path = format!("/images/json?filters={}", encode(&filter));
-    let resp = send_request(&path, false, false, None)?;
-    let result: Vec<ImageDescriptor> = serde_json::from_str(&resp)
-        .with_context(|| format!("Failed to parse find_images response json: {}", resp))?;
-
-    Ok(result)
-}
-
-fn create_container(container_name: &str, request: CreateContainer) -> Result<String> {
-    let path = format!("/containers/create?name={}", container_name);
-    let json = serde_json::to_string(&request)?;
-    let bytes = json.as_bytes();
-    let resp = send_request(&path, true, false, Some(bytes))?;
-    let result: CreateContainerResult = serde_json::from_str(&resp)
-        .with_context(|| format!("Failed to parse create_container response json: {}", resp))?;
-    Ok(result.id)
-}
-
-fn send_request(
-    path: &str,
-    post: bool,
-    delete: bool,
-    maybe_json_data: Option<&[u8]>,
-) -> Result<String> {
-    let mut easy = Easy::new();
-    easy.unix_socket("/var/run/docker.sock")?;
-    let url = format!("http://localhost{}", path);
-    easy.url(&url)?;
-
-    if post {
-        easy.post(true)?;
-        easy.post_field_size(0)?;
-    }
-
-    if delete {
-        easy.custom_request("DELETE")?;
-    }
-
-    let mut resp_data: Vec<u8> = Vec::new();
-    let read_data = |buf: &[u8]| {
-        resp_data.extend_from_slice(buf);
-        Ok(buf.len())
-    };
-
-    match maybe_json_data {
-        Some(mut req_data) => {
-            let mut list = List::new();
-            list.append("Content-Type: application/json")?;
-            easy.http_headers(list)?;
-            easy.post_field_size(req_data.len() as u64)?;
-            let mut transfer = easy.transfer();
-            transfer
-                .read_function(|buf| Ok(req_data.read(buf).unwrap_or(0)))
-                .unwrap();
-            transfer.write_function(read_data)?;
-            transfer.perform()?;
-        }
-        None => {
-            let mut transfer = easy.transfer();
-            transfer.write_function(read_data)?;
-            transfer.perform()?;
-        }
-    }
-
-    let data = std::str::from_utf8(&resp_data).unwrap();
-    match easy.response_code()? {
-        200..=204 => Ok(data.to_owned()),
-        _ => Err(anyhow!("Docker API call ({}) failed: {}", &path, data)),
-    }
+    DockerClient::from_env()?.find_images(reference)
+}
+
+/// Lists images matching given options
+///
+/// # Arguments
+/// * `options` - `all` flag along with image filters
+///
+/// # Examples
+/// ```no_run
+/// use docker_helper::ListImagesOptions;
+///
+/// let options = ListImagesOptions::new().dangling(true);
+/// for image in docker_helper::list_images(&options).unwrap() {
+///     println!("{} {}", image.id, image.size);
+/// }
+/// ```
+pub fn list_images(options: &ListImagesOptions) -> Result<Vec<ImageDescriptor>> {
+    DockerClient::from_env()?.list_images(options)
+}
+
+/// Inspects image with a given reference or ID
+///
+/// # Arguments
+/// * `name` - image reference (`image_name:version`) or ID
+///
+/// # Examples
+/// ```no_run
+/// let image = docker_helper::inspect_image("postgres:15").unwrap();
+/// let exposed_ports = image.config.exposed_ports.unwrap_or_default();
+/// assert!(exposed_ports.contains_key("5432/tcp"));
+/// ```
+pub fn inspect_image(name: &str) -> Result<ImageDetails> {
+    DockerClient::from_env()?.inspect_image(name)
+}
+
+/// Removes image along with its untagged parents, returning removed tags and layers
+///
+/// # Arguments
+/// * `name` - image reference (`image_name:version`) or ID
+/// * `options` - `force` and `noprune` flags
+///
+/// # Examples
+/// ```no_run
+/// use docker_helper::RemoveImageOptions;
+///
+/// let options = RemoveImageOptions::new().force(true);
+/// let result = docker_helper::remove_image("test-fixture:latest", &options);
+/// ```
+pub fn remove_image(name: &str, options: &RemoveImageOptions) -> Result<Vec<ImageDeleteItem>> {
+    DockerClient::from_env()?.remove_image(name, options)
 }
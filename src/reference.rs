@@ -0,0 +1,281 @@
+use crate::error::{Error, Result};
+use anyhow::anyhow;
+use std::fmt;
+use std::str::FromStr;
+
+/// Registry host used for images without an explicit registry
+pub const DEFAULT_REGISTRY: &str = "docker.io";
+
+/// Tag used for images referenced without a tag or digest
+pub const DEFAULT_TAG: &str = "latest";
+
+/// Parsed and normalized Docker image reference `[registry/]repository[:tag][@digest]`
+///
+/// # Examples
+/// ```
+/// use docker_helper::ImageReference;
+///
+/// let reference = ImageReference::parse("ubuntu:20.04").unwrap();
+/// assert_eq!(reference.registry(), "docker.io");
+/// assert_eq!(reference.repository(), "library/ubuntu");
+/// assert_eq!(reference.tag(), Some("20.04"));
+/// assert_eq!(reference.to_string(), "docker.io/library/ubuntu:20.04");
+///
+/// let reference = ImageReference::parse("localhost:5000/team/app@sha256:0123").unwrap();
+/// assert_eq!(reference.registry(), "localhost:5000");
+/// assert_eq!(reference.tag(), None);
+/// assert_eq!(reference.digest(), Some("sha256:0123"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ImageReference {
+    registry: String,
+    repository: String,
+    tag: Option<String>,
+    digest: Option<String>,
+}
+
+impl ImageReference {
+    /// Parses image reference applying Docker normalization rules: implicit `docker.io`
+    /// registry, `library/` namespace for official images and `latest` tag
+    pub fn parse(reference: &str) -> Result<ImageReference> {
+        let invalid = |reason: &str| -> Error {
+            anyhow!("Invalid image reference ({}): {}", reference, reason).into()
+        };
+
+        let (rest, digest) = match reference.split_once('@') {
+            Some((rest, digest)) => {
+                let (algorithm, hex) = digest
+                    .split_once(':')
+                    .ok_or_else(|| invalid("digest must be in the form algorithm:hex"))?;
+                if algorithm.is_empty() || hex.is_empty() {
+                    return Err(invalid("digest must be in the form algorithm:hex"));
+                }
+                (rest, Some(digest.to_owned()))
+            }
+            None => (reference, None),
+        };
+
+        let (name, tag) = match rest.rsplit_once(':') {
+            Some((name, tag)) if !tag.contains('/') => (name, Some(tag.to_owned())),
+            _ => (rest, None),
+        };
+        if name.is_empty() {
+            return Err(invalid("missing repository name"));
+        }
+        if let Some(tag) = &tag {
+            let valid = (1..=128).contains(&tag.len())
+                && !tag.starts_with(['.', '-'])
+                && tag
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+            if !valid {
+                return Err(invalid("malformed tag"));
+            }
+        }
+
+        let (registry, repository) = match name.split_once('/') {
+            Some((host, path)) if host.contains(['.', ':']) || host == "localhost" => {
+                (host.to_owned(), path.to_owned())
+            }
+            _ => (DEFAULT_REGISTRY.to_owned(), name.to_owned()),
+        };
+        let registry = match registry.as_str() {
+            "index.docker.io" => DEFAULT_REGISTRY.to_owned(),
+            _ => registry,
+        };
+        let repository = if registry == DEFAULT_REGISTRY && !repository.contains('/') {
+            format!("library/{}", repository)
+        } else {
+            repository
+        };
+
+        let valid = !repository.is_empty()
+            && repository.split('/').all(|component| {
+                !component.is_empty()
+                    && component.starts_with(|c: char| c.is_ascii_alphanumeric())
+                    && component.chars().all(|c| {
+                        c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '.' | '-')
+                    })
+            });
+        if !valid {
+            return Err(invalid("repository must be lowercase alphanumeric path"));
+        }
+
+        let tag = match (tag, &digest) {
+            (None, None) => Some(DEFAULT_TAG.to_owned()),
+            (tag, _) => tag,
+        };
+        Ok(ImageReference {
+            registry,
+            repository,
+            tag,
+            digest,
+        })
+    }
+
+    /// Registry host, e.g. `docker.io` or `localhost:5000`
+    pub fn registry(&self) -> &str {
+        &self.registry
+    }
+
+    /// Repository path within registry, e.g. `library/ubuntu`
+    pub fn repository(&self) -> &str {
+        &self.repository
+    }
+
+    /// Tag, `latest` unless explicitly set or image is referenced by digest only
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    /// Content digest, e.g. `sha256:...`
+    pub fn digest(&self) -> Option<&str> {
+        self.digest.as_deref()
+    }
+
+    /// Fully qualified name without tag or digest, e.g. `docker.io/library/ubuntu`
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.registry, self.repository)
+    }
+
+    /// Shortest name Docker CLI would display, e.g. `ubuntu` for `docker.io/library/ubuntu`
+    pub fn familiar_name(&self) -> String {
+        if self.registry != DEFAULT_REGISTRY {
+            return self.name();
+        }
+        self.repository
+            .strip_prefix("library/")
+            .filter(|name| !name.contains('/'))
+            .unwrap_or(&self.repository)
+            .to_owned()
+    }
+
+    /// Familiar name with tag and digest, e.g. `ubuntu:20.04`
+    pub fn familiar(&self) -> String {
+        self.format(&self.familiar_name())
+    }
+
+    /// Tag or digest in the form accepted by `tag` query parameter of the pull endpoint
+    pub(crate) fn tag_or_digest(&self) -> Option<&str> {
+        self.digest().or(self.tag())
+    }
+
+    fn format(&self, name: &str) -> String {
+        let mut result = name.to_owned();
+        if let Some(tag) = &self.tag {
+            result.push(':');
+            result.push_str(tag);
+        }
+        if let Some(digest) = &self.digest {
+            result.push('@');
+            result.push_str(digest);
+        }
+        result
+    }
+}
+
+impl fmt::Display for ImageReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format(&self.name()))
+    }
+}
+
+impl FromStr for ImageReference {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        ImageReference::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(reference: &str) -> ImageReference {
+        ImageReference::parse(reference).unwrap()
+    }
+
+    #[test]
+    fn registry_is_detected_by_dot_port_or_localhost() {
+        let reference = parse("localhost/app");
+        assert_eq!(reference.registry(), "localhost");
+        assert_eq!(reference.repository(), "app");
+
+        let reference = parse("host:5000/app");
+        assert_eq!(reference.registry(), "host:5000");
+        assert_eq!(reference.repository(), "app");
+
+        let reference = parse("my-registry/app");
+        assert_eq!(reference.registry(), "docker.io");
+        assert_eq!(reference.repository(), "my-registry/app");
+    }
+
+    #[test]
+    fn docker_hub_names_are_normalized() {
+        let reference = parse("index.docker.io/ubuntu");
+        assert_eq!(reference.registry(), "docker.io");
+        assert_eq!(reference.repository(), "library/ubuntu");
+        assert_eq!(reference, parse("docker.io/library/ubuntu:latest"));
+        assert_eq!(
+            parse("index.docker.io/team/app").name(),
+            "docker.io/team/app"
+        );
+    }
+
+    #[test]
+    fn tag_and_digest() {
+        let reference = parse("app:1.0@sha256:0123");
+        assert_eq!(reference.tag(), Some("1.0"));
+        assert_eq!(reference.digest(), Some("sha256:0123"));
+        assert_eq!(reference.tag_or_digest(), Some("sha256:0123"));
+
+        let reference = parse("app@sha256:0123");
+        assert_eq!(reference.tag(), None);
+        assert_eq!(reference.digest(), Some("sha256:0123"));
+        assert_eq!(reference.to_string(), "docker.io/library/app@sha256:0123");
+    }
+
+    #[test]
+    fn invalid_references_are_rejected() {
+        for reference in [
+            "",
+            "Ubuntu",
+            "team/App:1.0",
+            "ubuntu:",
+            "ubuntu:.hidden",
+            "ubuntu@sha256:",
+            "@sha256:0123",
+            ":1.0",
+            "localhost:5000/",
+        ] {
+            assert!(
+                ImageReference::parse(reference).is_err(),
+                "{} should be rejected",
+                reference
+            );
+        }
+        assert!(ImageReference::parse(&format!("ubuntu:{}", "a".repeat(128))).is_ok());
+        assert!(ImageReference::parse(&format!("ubuntu:{}", "a".repeat(129))).is_err());
+    }
+
+    #[test]
+    fn familiar_form_strips_default_registry_and_library() {
+        assert_eq!(
+            parse("docker.io/library/ubuntu:20.04").familiar(),
+            "ubuntu:20.04"
+        );
+        assert_eq!(
+            parse("index.docker.io/team/app").familiar(),
+            "team/app:latest"
+        );
+        assert_eq!(
+            parse("localhost:5000/app@sha256:0123").familiar(),
+            "localhost:5000/app@sha256:0123"
+        );
+        assert_eq!(
+            parse("library/ubuntu/nested").familiar(),
+            "library/ubuntu/nested:latest"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use crate::client::DockerClient;
+use crate::container::{set_default_resource_limits, ResourceLimits};
+use crate::error::Result;
+use crate::transport::{Request, Response, Transport};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// Variables cleared by [lock_env] and restored once the test is done
+const ENV_VARS: [&str; 7] = [
+    "DOCKER_HOST",
+    "DOCKER_TLS",
+    "DOCKER_TLS_VERIFY",
+    "DOCKER_CERT_PATH",
+    "DOCKER_CONTEXT",
+    "DOCKER_CONFIG",
+    "PATH",
+];
+
+/// Holds [ENV_LOCK], restoring environment variables changed by the test on drop
+pub(crate) struct EnvGuard {
+    saved: Vec<(&'static str, Option<OsString>)>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        for (name, value) in &self.saved {
+            match value {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+    }
+}
+
+/// Serializes tests reading or changing process environment such as `DOCKER_CONFIG`,
+/// clearing Docker variables until the returned guard is dropped. `PATH` is kept
+/// as is but restored as well.
+pub(crate) fn lock_env() -> EnvGuard {
+    let lock = ENV_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let saved = ENV_VARS
+        .iter()
+        .map(|&name| (name, env::var_os(name)))
+        .collect();
+    for name in ENV_VARS.iter().filter(|&&name| name != "PATH") {
+        env::remove_var(name);
+    }
+    EnvGuard { saved, _lock: lock }
+}
+
+/// [lock_env] with `DOCKER_CONFIG` pointing to an empty directory, so that registry
+/// credentials configured on the machine running tests are not looked up
+pub(crate) fn lock_empty_config() -> (EnvGuard, TempDir) {
+    let guard = lock_env();
+    let config = TempDir::new();
+    env::set_var("DOCKER_CONFIG", config.path());
+    (guard, config)
+}
+
+static LIMITS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Holds [LIMITS_LOCK], resetting default resource limits on drop
+pub(crate) struct LimitsGuard {
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Drop for LimitsGuard {
+    fn drop(&mut self) {
+        set_default_resource_limits(ResourceLimits::default());
+    }
+}
+
+/// Serializes tests changing crate-wide default resource limits, applying `limits`
+/// until the returned guard is dropped
+pub(crate) fn lock_default_limits(limits: ResourceLimits) -> LimitsGuard {
+    let guard = LimitsGuard {
+        _lock: LIMITS_LOCK.lock().unwrap_or_else(PoisonError::into_inner),
+    };
+    set_default_resource_limits(limits);
+    guard
+}
+
+/// Directory removed on drop
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new() -> TempDir {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "docker-helper-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Writes file creating parent directories, returning its path
+    pub(crate) fn write(&self, path: &str, contents: &str) -> PathBuf {
+        let path = self.0.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+type Handler = Box<dyn Fn(&Request) -> Response + Send + Sync>;
+
+/// [Transport] answering with `handler` and recording every request
+struct MockTransport {
+    handler: Handler,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl Transport for MockTransport {
+    fn send(&self, request: Request) -> Result<Response> {
+        let response = (self.handler)(&request);
+        self.requests.lock().unwrap().push(request);
+        Ok(response)
+    }
+}
+
+/// Client backed by a mock daemon along with the log of requests sent to it
+pub(crate) fn mock_client(
+    handler: impl Fn(&Request) -> Response + Send + Sync + 'static,
+) -> (DockerClient, Arc<Mutex<Vec<Request>>>) {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let transport = MockTransport {
+        handler: Box::new(handler),
+        requests: requests.clone(),
+    };
+    (DockerClient::with_transport(transport), requests)
+}
+
+/// `path_and_query` of every recorded request
+pub(crate) fn sent(requests: &Mutex<Vec<Request>>) -> Vec<String> {
+    requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|request| format!("{} {}", request.method.as_str(), request.path_and_query()))
+        .collect()
+}
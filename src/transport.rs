@@ -0,0 +1,494 @@
+use crate::endpoint::Endpoint;
+use crate::error::Result;
+use anyhow::{anyhow, Context};
+use curl::easy::{Easy, List};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, Cursor, Read};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+use urlencoding::encode;
+
+/// HTTP method of a Docker API call
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+        }
+    }
+}
+
+/// Docker API request passed to a [Transport]
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: Method,
+    /// Endpoint path, e.g. `/containers/json`
+    pub path: String,
+    /// Query parameters, not yet URL encoded
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl Request {
+    pub fn new(method: Method, path: impl Into<String>) -> Request {
+        Request {
+            method,
+            path: path.into(),
+            query: Vec::new(),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    /// Appends query parameter
+    pub fn query(mut self, name: &str, value: impl Into<String>) -> Request {
+        self.query.push((name.to_owned(), value.into()));
+        self
+    }
+
+    /// Appends request header
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Request {
+        self.headers.push((name.to_owned(), value.into()));
+        self
+    }
+
+    /// Sets raw request body along with its `Content-Type`
+    pub fn body(self, content_type: &str, body: Vec<u8>) -> Request {
+        let mut request = self.header("Content-Type", content_type);
+        request.body = Some(body);
+        request
+    }
+
+    /// Serializes `value` as JSON request body
+    pub fn json<T: Serialize>(self, value: &T) -> Result<Request> {
+        Ok(self.body("application/json", serde_json::to_vec(value)?))
+    }
+
+    /// Path with URL encoded query string appended
+    pub fn path_and_query(&self) -> String {
+        if self.query.is_empty() {
+            return self.path.clone();
+        }
+        let query: Vec<String> = self
+            .query
+            .iter()
+            .map(|(name, value)| format!("{}={}", encode(name), encode(value)))
+            .collect();
+        format!("{}?{}", self.path, query.join("&"))
+    }
+}
+
+/// Docker API response returned by a [Transport]. `body` is streamed as it arrives.
+pub struct Response {
+    pub status: u32,
+    pub headers: Vec<(String, String)>,
+    pub body: Box<dyn Read + Send>,
+}
+
+impl Response {
+    /// Creates response with in-memory body, handy for mock transports
+    ///
+    /// # Examples
+    /// ```
+    /// let response = docker_helper::Response::new(200, r#"{"Id":"6fe66725ed81"}"#);
+    /// assert_eq!(response.text().unwrap(), r#"{"Id":"6fe66725ed81"}"#);
+    /// ```
+    pub fn new(status: u32, body: impl Into<Vec<u8>>) -> Response {
+        Response {
+            status,
+            headers: Vec::new(),
+            body: Box::new(Cursor::new(body.into())),
+        }
+    }
+
+    /// Value of the first header with a given case-insensitive `name`
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Reads the whole body as UTF-8 string
+    pub fn text(mut self) -> Result<String> {
+        let mut data = Vec::new();
+        self.body
+            .read_to_end(&mut data)
+            .context("Failed to read Docker API response")?;
+        Ok(String::from_utf8(data)?)
+    }
+}
+
+impl fmt::Debug for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Response")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// HTTP layer used by [crate::DockerClient] to talk to Docker daemon.
+/// Implement it to route calls elsewhere or to mock the daemon in tests.
+///
+/// # Examples
+/// ```
+/// use docker_helper::{DockerClient, Request, Response, Transport};
+///
+/// struct NoImages;
+///
+/// impl Transport for NoImages {
+///     fn send(&self, _request: Request) -> docker_helper::Result<Response> {
+///         Ok(Response::new(200, "[]"))
+///     }
+/// }
+///
+/// let client = DockerClient::with_transport(NoImages);
+/// assert!(client.find_images("ubuntu:20.04").unwrap().is_empty());
+/// ```
+pub trait Transport: Send + Sync {
+    /// Sends `request` returning as soon as response status and headers are received
+    fn send(&self, request: Request) -> Result<Response>;
+}
+
+/// Default [Transport] built on top of [curl::easy::Easy]
+#[derive(Clone, Debug, Default)]
+pub struct CurlTransport {
+    endpoint: Endpoint,
+}
+
+impl CurlTransport {
+    pub fn new(endpoint: Endpoint) -> CurlTransport {
+        CurlTransport { endpoint }
+    }
+
+    pub fn endpoint(&self) -> &Endpoint {
+        &self.endpoint
+    }
+
+    fn prepare(&self, request: &Request) -> Result<Easy> {
+        let mut easy = Easy::new();
+        let path = request.path_and_query();
+        let url = match &self.endpoint {
+            Endpoint::Unix(socket) => {
+                easy.unix_socket_path(Some(socket))?;
+                format!("http://localhost{}", path)
+            }
+            Endpoint::Tcp(address) => format!("http://{}{}", address, path),
+            Endpoint::Tls { address, config } => {
+                if let Some(ca) = &config.ca {
+                    easy.cainfo(ca)?;
+                }
+                if let Some(cert) = &config.cert {
+                    easy.ssl_cert(cert)?;
+                }
+                if let Some(key) = &config.key {
+                    easy.ssl_key(key)?;
+                }
+                easy.ssl_verify_peer(config.verify)?;
+                easy.ssl_verify_host(config.verify)?;
+                format!("https://{}{}", address, path)
+            }
+        };
+        easy.url(&url)?;
+
+        match request.method {
+            Method::Get => {}
+            Method::Head => easy.nobody(true)?,
+            Method::Post => easy.post(true)?,
+            Method::Put | Method::Delete => easy.custom_request(request.method.as_str())?,
+        }
+        match &request.body {
+            Some(body) => easy.post_fields_copy(body)?,
+            None if request.method == Method::Post => easy.post_field_size(0)?,
+            None => {}
+        }
+
+        let mut list = List::new();
+        // Stops curl from waiting for `100 Continue` before sending large bodies
+        list.append("Expect:")?;
+        for (name, value) in &request.headers {
+            list.append(&format!("{}: {}", name, value))?;
+        }
+        easy.http_headers(list)?;
+        Ok(easy)
+    }
+}
+
+impl Transport for CurlTransport {
+    fn send(&self, request: Request) -> Result<Response> {
+        let easy = self.prepare(&request)?;
+        let (head_tx, head_rx) = mpsc::sync_channel(1);
+        let (body_tx, body_rx) = mpsc::sync_channel(16);
+        thread::spawn(move || perform(easy, head_tx, body_tx));
+
+        let (status, headers) = head_rx
+            .recv()
+            .map_err(|_| anyhow!("Docker API call ({}) aborted", request.path))??;
+        Ok(Response {
+            status,
+            headers,
+            body: Box::new(ChannelReader {
+                chunks: body_rx,
+                chunk: Cursor::new(Vec::new()),
+            }),
+        })
+    }
+}
+
+type Head = (u32, Vec<(String, String)>);
+
+#[derive(Default)]
+struct HeadState {
+    status: u32,
+    headers: Vec<(String, String)>,
+    sent: bool,
+}
+
+/// Runs the transfer on a dedicated thread, handing over status and headers
+/// once received and then streaming body chunks
+fn perform(
+    mut easy: Easy,
+    head_tx: SyncSender<Result<Head>>,
+    body_tx: SyncSender<io::Result<Vec<u8>>>,
+) {
+    let state = RefCell::new(HeadState::default());
+    let result = transfer(&mut easy, &state, &head_tx, &body_tx);
+    let mut state = state.into_inner();
+    match result {
+        Ok(()) if !state.sent => {
+            let status = easy.response_code().unwrap_or(state.status);
+            let _ = head_tx.send(Ok((status, std::mem::take(&mut state.headers))));
+        }
+        Ok(()) => {}
+        Err(e) if !state.sent => {
+            let _ = head_tx.send(Err(e.into()));
+        }
+        Err(e) => {
+            let _ = body_tx.send(Err(io::Error::other(e)));
+        }
+    }
+}
+
+fn transfer(
+    easy: &mut Easy,
+    state: &RefCell<HeadState>,
+    head_tx: &SyncSender<Result<Head>>,
+    body_tx: &SyncSender<io::Result<Vec<u8>>>,
+) -> Result<(), curl::Error> {
+    let mut transfer = easy.transfer();
+    transfer.header_function(|line| {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end();
+        let mut state = state.borrow_mut();
+        if line.starts_with("HTTP/") {
+            state.status = line
+                .split_whitespace()
+                .nth(1)
+                .and_then(|code| code.parse().ok())
+                .unwrap_or(0);
+            state.headers.clear();
+        } else if let Some((name, value)) = line.split_once(':') {
+            state
+                .headers
+                .push((name.trim().to_owned(), value.trim().to_owned()));
+        } else if line.is_empty() && state.status >= 200 && !state.sent {
+            state.sent = true;
+            let head = (state.status, state.headers.clone());
+            return head_tx.send(Ok(head)).is_ok();
+        }
+        true
+    })?;
+    transfer.write_function(|buf| match body_tx.send(Ok(buf.to_vec())) {
+        Ok(()) => Ok(buf.len()),
+        // Response was dropped, abort the transfer
+        Err(_) => Ok(0),
+    })?;
+    transfer.perform()
+}
+
+struct ChannelReader {
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    chunk: Cursor<Vec<u8>>,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.chunk.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            match self.chunks.recv() {
+                Ok(chunk) => self.chunk = Cursor::new(chunk?),
+                Err(_) => return Ok(0),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::DockerClient;
+    use crate::test_support::{lock_env, TempDir};
+    use std::env;
+    use std::fs;
+    use std::io::{BufRead, BufReader};
+    use std::path::Path;
+    use std::process::{Child, Command, Stdio};
+
+    /// Stand-in for a TLS protected daemon, answering every request with an empty list
+    /// and rejecting clients without a certificate signed by `ca.pem`
+    const TLS_DAEMON: &str = r#"
+import http.server, ssl
+class Handler(http.server.BaseHTTPRequestHandler):
+    def do_GET(self):
+        self.send_response(200)
+        self.send_header("Content-Type", "application/json")
+        self.send_header("Content-Length", "2")
+        self.end_headers()
+        self.wfile.write(b"[]")
+    def log_message(self, *args):
+        pass
+context = ssl.SSLContext(ssl.PROTOCOL_TLS_SERVER)
+context.load_cert_chain("server.pem", "server-key.pem")
+context.load_verify_locations("ca.pem")
+context.verify_mode = ssl.CERT_REQUIRED
+server = http.server.HTTPServer(("127.0.0.1", 0), Handler)
+server.socket = context.wrap_socket(server.socket, server_side=True)
+print(server.server_address[1], flush=True)
+server.serve_forever()
+"#;
+
+    fn available(program: &str, arg: &str) -> bool {
+        Command::new(program)
+            .arg(arg)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok()
+    }
+
+    /// Runs `openssl` with whitespace separated `args` in `dir`
+    fn openssl(dir: &Path, args: &str) {
+        let output = Command::new("openssl")
+            .args(args.split_whitespace())
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "openssl {}: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    /// Issues certificate `name.pem` with key `name-key.pem` signed by `ca.pem`
+    fn issue(dir: &Path, name: &str, extensions: &str) {
+        fs::write(dir.join(format!("{}.ext", name)), extensions).unwrap();
+        openssl(
+            dir,
+            &format!(
+                "req -newkey rsa:2048 -nodes -keyout {0}-key.pem -subj /CN={0} -out {0}.csr",
+                name
+            ),
+        );
+        openssl(
+            dir,
+            &format!(
+                "x509 -req -in {0}.csr -CA ca.pem -CAkey ca-key.pem -CAcreateserial -days 1 \
+                 -extfile {0}.ext -out {0}.pem",
+                name
+            ),
+        );
+    }
+
+    /// Daemon process killed on drop
+    struct Daemon(Child);
+
+    impl Drop for Daemon {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    /// Starts [TLS_DAEMON] with certificates from `dir`, returning it along with its port
+    fn start_daemon(dir: &Path) -> (Daemon, u16) {
+        let mut child = Command::new("python3")
+            .args(["-c", TLS_DAEMON])
+            .current_dir(dir)
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let daemon = Daemon(child);
+        let mut port = String::new();
+        BufReader::new(stdout).read_line(&mut port).unwrap();
+        (daemon, port.trim().parse().unwrap())
+    }
+
+    #[test]
+    fn tls_verify_uses_client_certificates_from_cert_path() {
+        if !available("openssl", "version") || !available("python3", "--version") {
+            eprintln!("openssl or python3 not available, skipping");
+            return;
+        }
+        let _env = lock_env();
+        let server = TempDir::new();
+        openssl(
+            server.path(),
+            "req -x509 -newkey rsa:2048 -nodes -keyout ca-key.pem \
+             -subj /CN=docker-helper-test-ca -days 1 -out ca.pem",
+        );
+        issue(server.path(), "server", "subjectAltName=IP:127.0.0.1\n");
+        issue(server.path(), "client", "extendedKeyUsage=clientAuth\n");
+        // Daemon presents the whole chain, so that a client without `ca.pem`
+        // sees a self-signed certificate rather than an unknown issuer
+        let chain = fs::read_to_string(server.path().join("server.pem")).unwrap()
+            + &fs::read_to_string(server.path().join("ca.pem")).unwrap();
+        fs::write(server.path().join("server.pem"), chain).unwrap();
+        let (_daemon, port) = start_daemon(server.path());
+
+        let certs = TempDir::new();
+        for (from, to) in [
+            ("ca.pem", "ca.pem"),
+            ("client.pem", "cert.pem"),
+            ("client-key.pem", "key.pem"),
+        ] {
+            fs::copy(server.path().join(from), certs.path().join(to)).unwrap();
+        }
+        env::set_var("DOCKER_HOST", format!("tcp://127.0.0.1:{}", port));
+        env::set_var("DOCKER_TLS_VERIFY", "1");
+        env::set_var("DOCKER_CERT_PATH", certs.path());
+
+        let client = DockerClient::from_env().unwrap();
+        assert!(client.find_images("ubuntu:20.04").unwrap().is_empty());
+
+        fs::remove_file(certs.path().join("ca.pem")).unwrap();
+        let client = DockerClient::from_env().unwrap();
+        let error = client.find_images("ubuntu:20.04").unwrap_err().to_string();
+        // OpenSSL before 3.0 spells it "self signed"
+        assert!(
+            error.contains("self-signed") || error.contains("self signed"),
+            "{}",
+            error
+        );
+    }
+}
@@ -1,58 +1,668 @@
-use serde::{Deserialize, Serialize};
+use crate::container::{EmptyObject, Healthcheck, HostConfig, PortBinding};
+use crate::reference::ImageReference;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 
-#[derive(Serialize)]
-pub struct PortBinding {
-    #[serde(rename = "HostPort")]
-    pub host_port: String,
-}
-
-#[derive(Serialize)]
-pub struct CreateContainer {
-    #[serde(rename = "Image")]
-    pub image: String,
-    #[serde(rename = "NetworkMode")]
-    pub network_mode: String,
-}
-
 #[derive(Deserialize, Debug)]
 pub struct CreateContainerResult {
     #[serde(rename = "Id")]
     pub id: String,
 }
 
+/// Single progress message streamed by the daemon, e.g. while pulling or building an image
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ProgressEvent {
+    /// Human readable status, e.g. `Downloading` or `Pull complete`
+    pub status: Option<String>,
+    /// Layer ID the event relates to
+    pub id: Option<String>,
+    /// Pre-rendered progress bar
+    pub progress: Option<String>,
+    #[serde(rename = "progressDetail")]
+    pub progress_detail: Option<ProgressDetail>,
+    /// Build output, e.g. `Step 1/3 : FROM ubuntu:20.04`
+    pub stream: Option<String>,
+    /// Auxiliary data such as ID of the built image or digest of the pushed one
+    pub aux: Option<Value>,
+    pub error: Option<String>,
+    #[serde(rename = "errorDetail")]
+    pub error_detail: Option<ErrorDetail>,
+}
+
+impl ProgressEvent {
+    /// Error reported by the daemon in the middle of a streamed response
+    pub fn error_message(&self) -> Option<&str> {
+        self.error_detail
+            .as_ref()
+            .map(|detail| detail.message.as_str())
+            .or(self.error.as_deref())
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ProgressDetail {
+    pub current: Option<u64>,
+    pub total: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ErrorDetail {
+    pub code: Option<i64>,
+    #[serde(default)]
+    pub message: String,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct ImageDescriptor {
     #[serde(rename = "Id")]
     pub id: String,
+    #[serde(rename = "ParentId", default)]
+    pub parent_id: String,
+    /// Tags such as `ubuntu:20.04`, empty for dangling images
+    #[serde(rename = "RepoTags")]
+    pub repo_tags: Option<Vec<String>>,
+    #[serde(rename = "RepoDigests")]
+    pub repo_digests: Option<Vec<String>>,
+    /// Unix timestamp
+    #[serde(rename = "Created", default)]
+    pub created: i64,
+    #[serde(rename = "Size", default)]
+    pub size: i64,
+    #[serde(rename = "Labels")]
+    pub labels: Option<HashMap<String, String>>,
+    /// Number of containers using the image, `-1` if not computed
+    #[serde(rename = "Containers", default)]
+    pub containers: i64,
 }
 
-#[derive(Serialize)]
+/// Filters of the image list endpoint, each one matching any of its values
+#[derive(Serialize, Debug, Clone, Default)]
 pub struct ImageFilter {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub reference: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dangling: Vec<String>,
+    /// `key` or `key=value`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub label: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub since: Vec<String>,
+}
+
+/// Options of [crate::list_images]
+///
+/// # Examples
+/// ```
+/// use docker_helper::ListImagesOptions;
+///
+/// let options = ListImagesOptions::new()
+///     .reference("ubuntu:*")
+///     .label("com.example.suite=integration");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ListImagesOptions {
+    /// Include intermediate image layers
+    pub all: bool,
+    pub filters: ImageFilter,
+}
+
+impl ListImagesOptions {
+    pub fn new() -> ListImagesOptions {
+        ListImagesOptions::default()
+    }
+
+    pub fn all(mut self, all: bool) -> ListImagesOptions {
+        self.all = all;
+        self
+    }
+
+    /// Image reference such as `ubuntu:20.04`, wildcard patterns like `ubuntu:*` are allowed
+    pub fn reference(mut self, reference: &str) -> ListImagesOptions {
+        // Patterns such as `ubuntu:*` are not valid references and are passed as is
+        let reference = ImageReference::parse(reference)
+            .map(|reference| reference.familiar())
+            .unwrap_or_else(|_| reference.to_owned());
+        self.filters.reference.push(reference);
+        self
+    }
+
+    /// Only untagged images when `true`, only tagged ones when `false`
+    pub fn dangling(mut self, dangling: bool) -> ListImagesOptions {
+        self.filters.dangling.push(dangling.to_string());
+        self
+    }
+
+    /// Label `key` or `key=value`
+    pub fn label(mut self, label: &str) -> ListImagesOptions {
+        self.filters.label.push(label.to_owned());
+        self
+    }
+
+    /// Images created before a given image reference or ID
+    pub fn before(mut self, image: &str) -> ListImagesOptions {
+        self.filters.before.push(image.to_owned());
+        self
+    }
+
+    /// Images created after a given image reference or ID
+    pub fn since(mut self, image: &str) -> ListImagesOptions {
+        self.filters.since.push(image.to_owned());
+        self
+    }
+}
+
+/// Low-level image information returned by image inspection
+#[derive(Deserialize, Debug)]
+pub struct ImageDetails {
+    #[serde(rename = "Id")]
+    pub id: String,
+    #[serde(rename = "RepoTags")]
+    pub repo_tags: Option<Vec<String>>,
+    /// Content addressable references, e.g. `ubuntu@sha256:...`
+    #[serde(rename = "RepoDigests")]
+    pub repo_digests: Option<Vec<String>>,
+    #[serde(rename = "Parent", default)]
+    pub parent: String,
+    #[serde(rename = "Comment", default)]
+    pub comment: String,
+    /// RFC 3339 timestamp
+    #[serde(rename = "Created", default)]
+    pub created: String,
+    #[serde(rename = "Author", default)]
+    pub author: String,
+    #[serde(rename = "Architecture", default)]
+    pub architecture: String,
+    #[serde(rename = "Variant")]
+    pub variant: Option<String>,
+    #[serde(rename = "Os", default)]
+    pub os: String,
+    #[serde(rename = "Size", default)]
+    pub size: i64,
+    /// Default configuration of containers created from the image
+    #[serde(rename = "Config", default)]
+    pub config: ContainerConfig,
+    /// Remaining fields not covered above, e.g. `RootFS` or `GraphDriver`
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// Options of [crate::remove_image]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RemoveImageOptions {
+    /// Remove image even if it has multiple tags or is used by stopped containers
+    pub force: bool,
+    /// Keep untagged parent images
+    pub noprune: bool,
+}
+
+impl RemoveImageOptions {
+    pub fn new() -> RemoveImageOptions {
+        RemoveImageOptions::default()
+    }
+
+    pub fn force(mut self, force: bool) -> RemoveImageOptions {
+        self.force = force;
+        self
+    }
+
+    pub fn noprune(mut self, noprune: bool) -> RemoveImageOptions {
+        self.noprune = noprune;
+        self
+    }
 }
 
-#[derive(Serialize)]
+/// Single tag removed or image layer deleted by [crate::remove_image]
+#[derive(Deserialize, Debug)]
+pub struct ImageDeleteItem {
+    #[serde(rename = "Untagged")]
+    pub untagged: Option<String>,
+    #[serde(rename = "Deleted")]
+    pub deleted: Option<String>,
+}
+
+/// Filters of the container list endpoint, each one matching any of its values
+#[derive(Serialize, Debug, Clone, Default)]
 pub struct ContainerFilter {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub id: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub name: Vec<String>,
+    /// `key` or `key=value`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub label: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub status: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ancestor: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub network: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub volume: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exited: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub health: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub since: Vec<String>,
+}
+
+/// Options of [crate::list_containers]
+///
+/// # Examples
+/// ```
+/// use docker_helper::ListContainersOptions;
+///
+/// let options = ListContainersOptions::new()
+///     .all(true)
+///     .label("com.example.suite=integration")
+///     .status("exited")
+///     .status("created");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ListContainersOptions {
+    /// Include stopped containers, only running ones are listed otherwise
+    pub all: bool,
+    /// Return only this many most recently created containers, including stopped ones
+    pub limit: Option<usize>,
+    /// Report `SizeRw` and `SizeRootFs`
+    pub size: bool,
+    pub filters: ContainerFilter,
+}
+
+impl ListContainersOptions {
+    pub fn new() -> ListContainersOptions {
+        ListContainersOptions::default()
+    }
+
+    pub fn all(mut self, all: bool) -> ListContainersOptions {
+        self.all = all;
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> ListContainersOptions {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn size(mut self, size: bool) -> ListContainersOptions {
+        self.size = size;
+        self
+    }
+
+    /// Full or partial container ID
+    pub fn id(mut self, id: &str) -> ListContainersOptions {
+        self.filters.id.push(id.to_owned());
+        self
+    }
+
+    /// Full or partial container name
+    pub fn name(mut self, name: &str) -> ListContainersOptions {
+        self.filters.name.push(name.to_owned());
+        self
+    }
+
+    /// Label `key` or `key=value`
+    pub fn label(mut self, label: &str) -> ListContainersOptions {
+        self.filters.label.push(label.to_owned());
+        self
+    }
+
+    /// `created`, `restarting`, `running`, `removing`, `paused`, `exited` or `dead`
+    pub fn status(mut self, status: &str) -> ListContainersOptions {
+        self.filters.status.push(status.to_owned());
+        self
+    }
+
+    /// Image name, ID or digest the containers were created from, including descendants
+    pub fn ancestor(mut self, image: &str) -> ListContainersOptions {
+        self.filters.ancestor.push(image.to_owned());
+        self
+    }
+
+    /// Network ID or name
+    pub fn network(mut self, network: &str) -> ListContainersOptions {
+        self.filters.network.push(network.to_owned());
+        self
+    }
+
+    /// Volume name or mount point destination
+    pub fn volume(mut self, volume: &str) -> ListContainersOptions {
+        self.filters.volume.push(volume.to_owned());
+        self
+    }
+
+    /// Exit code, only useful along with [ListContainersOptions::all]
+    pub fn exited(mut self, exit_code: i64) -> ListContainersOptions {
+        self.filters.exited.push(exit_code.to_string());
+        self
+    }
+
+    /// `starting`, `healthy`, `unhealthy` or `none`
+    pub fn health(mut self, health: &str) -> ListContainersOptions {
+        self.filters.health.push(health.to_owned());
+        self
+    }
+
+    /// Containers created before a given container ID or name
+    pub fn before(mut self, container: &str) -> ListContainersOptions {
+        self.filters.before.push(container.to_owned());
+        self
+    }
+
+    /// Containers created after a given container ID or name
+    pub fn since(mut self, container: &str) -> ListContainersOptions {
+        self.filters.since.push(container.to_owned());
+        self
+    }
 }
 
 #[derive(Deserialize, Debug)]
 pub struct ContainerDescriptor {
     #[serde(rename = "Id")]
     pub id: String,
+    /// Container names with a leading `/`, e.g. `/test`
+    #[serde(rename = "Names", default, deserialize_with = "null_as_default")]
+    pub names: Vec<String>,
+    /// Image name as given on container creation
+    #[serde(rename = "Image", default)]
+    pub image: String,
+    #[serde(rename = "ImageID", default)]
+    pub image_id: String,
+    #[serde(rename = "Command", default)]
+    pub command: String,
+    /// Unix timestamp
+    #[serde(rename = "Created", default)]
+    pub created: i64,
+    /// `created`, `running`, `paused`, `restarting`, `removing`, `exited` or `dead`
+    #[serde(rename = "State", default)]
+    pub state: String,
+    /// Human readable status, e.g. `Up 5 minutes` or `Exited (0) 2 hours ago`
+    #[serde(rename = "Status", default)]
+    pub status: String,
+    #[serde(rename = "Labels")]
+    pub labels: Option<HashMap<String, String>>,
+    #[serde(rename = "Ports", default, deserialize_with = "null_as_default")]
+    pub ports: Vec<ContainerPort>,
+    /// Only reported when requested with [ListContainersOptions::size]
+    #[serde(rename = "SizeRw")]
+    pub size_rw: Option<i64>,
+    /// Only reported when requested with [ListContainersOptions::size]
+    #[serde(rename = "SizeRootFs")]
+    pub size_root_fs: Option<i64>,
+    #[serde(rename = "Mounts", default, deserialize_with = "null_as_default")]
+    pub mounts: Vec<MountPoint>,
     #[serde(rename = "NetworkSettings")]
     pub network_settings: NetworkSettings,
 }
 
+/// Exposed container port along with its host binding, if published
+#[derive(Deserialize, Debug, Clone)]
+pub struct ContainerPort {
+    /// Host IP the port is published on
+    #[serde(rename = "IP")]
+    pub ip: Option<String>,
+    #[serde(rename = "PrivatePort")]
+    pub private_port: u16,
+    #[serde(rename = "PublicPort")]
+    pub public_port: Option<u16>,
+    /// `tcp`, `udp` or `sctp`
+    #[serde(rename = "Type")]
+    pub port_type: String,
+}
+
+/// Low-level container information returned by container inspection
+#[derive(Deserialize, Debug)]
+pub struct ContainerDetails {
+    #[serde(rename = "Id")]
+    pub id: String,
+    /// Container name with a leading `/`, e.g. `/test`
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Created")]
+    pub created: String,
+    /// ID of the image the container was created from
+    #[serde(rename = "Image")]
+    pub image: String,
+    #[serde(rename = "Path", default)]
+    pub path: String,
+    #[serde(rename = "Args", default, deserialize_with = "null_as_default")]
+    pub args: Vec<String>,
+    #[serde(rename = "RestartCount", default)]
+    pub restart_count: i64,
+    #[serde(rename = "Platform", default)]
+    pub platform: String,
+    #[serde(rename = "State")]
+    pub state: ContainerState,
+    #[serde(rename = "Config")]
+    pub config: ContainerConfig,
+    #[serde(rename = "HostConfig", default)]
+    pub host_config: HostConfig,
+    #[serde(rename = "Mounts", default, deserialize_with = "null_as_default")]
+    pub mounts: Vec<MountPoint>,
+    #[serde(rename = "NetworkSettings")]
+    pub network_settings: NetworkSettings,
+    /// Remaining fields not covered above, e.g. `GraphDriver` or `LogPath`
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ContainerState {
+    /// `created`, `running`, `paused`, `restarting`, `removing`, `exited` or `dead`
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "Running")]
+    pub running: bool,
+    #[serde(rename = "Paused", default)]
+    pub paused: bool,
+    #[serde(rename = "Restarting", default)]
+    pub restarting: bool,
+    #[serde(rename = "OOMKilled", default)]
+    pub oom_killed: bool,
+    #[serde(rename = "Dead", default)]
+    pub dead: bool,
+    #[serde(rename = "Pid", default)]
+    pub pid: i64,
+    #[serde(rename = "ExitCode")]
+    pub exit_code: i64,
+    #[serde(rename = "Error", default)]
+    pub error: String,
+    /// RFC 3339 timestamp, `0001-01-01T00:00:00Z` if never started
+    #[serde(rename = "StartedAt", default)]
+    pub started_at: String,
+    /// RFC 3339 timestamp, `0001-01-01T00:00:00Z` if never finished
+    #[serde(rename = "FinishedAt", default)]
+    pub finished_at: String,
+    /// Present only for containers with a healthcheck
+    #[serde(rename = "Health")]
+    pub health: Option<Health>,
+}
+
+/// Container configuration as reported by inspection, merged with image defaults
+#[derive(Deserialize, Debug, Default)]
+pub struct ContainerConfig {
+    #[serde(rename = "Hostname", default)]
+    pub hostname: String,
+    #[serde(rename = "Domainname", default)]
+    pub domainname: String,
+    #[serde(rename = "User", default)]
+    pub user: String,
+    #[serde(rename = "Image", default)]
+    pub image: String,
+    #[serde(rename = "Env")]
+    pub env: Option<Vec<String>>,
+    #[serde(rename = "Cmd")]
+    pub cmd: Option<Vec<String>>,
+    #[serde(rename = "Entrypoint")]
+    pub entrypoint: Option<Vec<String>>,
+    #[serde(rename = "WorkingDir", default)]
+    pub working_dir: String,
+    #[serde(rename = "Labels")]
+    pub labels: Option<HashMap<String, String>>,
+    #[serde(rename = "ExposedPorts")]
+    pub exposed_ports: Option<HashMap<String, EmptyObject>>,
+    #[serde(rename = "Volumes")]
+    pub volumes: Option<HashMap<String, EmptyObject>>,
+    #[serde(rename = "Tty", default)]
+    pub tty: bool,
+    #[serde(rename = "OpenStdin", default)]
+    pub open_stdin: bool,
+    #[serde(rename = "StopSignal")]
+    pub stop_signal: Option<String>,
+    #[serde(rename = "Healthcheck")]
+    pub healthcheck: Option<Healthcheck>,
+}
+
+/// Mount attached to a running container
+#[derive(Deserialize, Debug)]
+pub struct MountPoint {
+    /// `bind`, `volume`, `tmpfs`, `npipe` or `cluster`
+    #[serde(rename = "Type")]
+    pub mount_type: String,
+    /// Volume name, empty for other mount types
+    #[serde(rename = "Name", default)]
+    pub name: String,
+    #[serde(rename = "Source", default)]
+    pub source: String,
+    #[serde(rename = "Destination")]
+    pub destination: String,
+    #[serde(rename = "Driver", default)]
+    pub driver: String,
+    #[serde(rename = "Mode", default)]
+    pub mode: String,
+    #[serde(rename = "RW", default)]
+    pub read_write: bool,
+    #[serde(rename = "Propagation", default)]
+    pub propagation: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Health {
+    /// `starting`, `healthy` or `unhealthy`
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "FailingStreak", default)]
+    pub failing_streak: i64,
+    #[serde(rename = "Log", default, deserialize_with = "null_as_default")]
+    pub log: Vec<HealthLog>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct HealthLog {
+    #[serde(rename = "Start")]
+    pub start: String,
+    #[serde(rename = "End")]
+    pub end: String,
+    #[serde(rename = "ExitCode")]
+    pub exit_code: i64,
+    #[serde(rename = "Output")]
+    pub output: String,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct NetworkSettings {
     #[serde(rename = "Networks")]
     pub networks: HashMap<String, Network>,
+    /// Published ports keyed by `port/protocol`, `None` for exposed but unpublished ones.
+    /// Only reported by container inspection.
+    #[serde(rename = "Ports", default, deserialize_with = "null_as_default")]
+    pub ports: HashMap<String, Option<Vec<PortBinding>>>,
+    #[serde(rename = "SandboxID", default)]
+    pub sandbox_id: String,
+    #[serde(rename = "SandboxKey", default)]
+    pub sandbox_key: String,
+    /// Remaining deprecated fields of the default bridge network, e.g. `IPAddress`
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Network {
+    #[serde(rename = "NetworkID", default)]
+    pub network_id: String,
+    #[serde(rename = "EndpointID", default)]
+    pub endpoint_id: String,
     #[serde(rename = "IPAddress")]
     pub ip_address: String,
+    #[serde(rename = "IPPrefixLen", default)]
+    pub ip_prefix_len: i64,
+    #[serde(rename = "Gateway", default)]
+    pub gateway: String,
+    #[serde(rename = "GlobalIPv6Address", default)]
+    pub global_ipv6_address: String,
+    #[serde(rename = "GlobalIPv6PrefixLen", default)]
+    pub global_ipv6_prefix_len: i64,
+    #[serde(rename = "IPv6Gateway", default)]
+    pub ipv6_gateway: String,
+    #[serde(rename = "MacAddress", default)]
+    pub mac_address: String,
+    #[serde(rename = "Aliases")]
+    pub aliases: Option<Vec<String>>,
+    #[serde(rename = "Links")]
+    pub links: Option<Vec<String>>,
+}
+
+/// Deserializes `null` the same way as a missing field, the daemon reports empty
+/// collections as `null` in places, e.g. ports of a container that is not running
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn null_network_ports_deserialize_as_empty() {
+        let settings: NetworkSettings =
+            serde_json::from_str(r#"{"Networks":{},"Ports":null}"#).unwrap();
+        assert!(settings.ports.is_empty());
+    }
+
+    #[test]
+    fn null_details_collections_deserialize_as_empty() {
+        let details: ContainerDetails = serde_json::from_str(
+            r#"{"Id":"6fe66725ed81","Name":"/test","Created":"2023-01-10T10:00:00Z",
+                "Image":"sha256:1","Args":null,"Mounts":null,
+                "State":{"Status":"created","Running":false,"ExitCode":0},
+                "Config":{},"NetworkSettings":{"Networks":{},"Ports":null}}"#,
+        )
+        .unwrap();
+        assert!(details.args.is_empty());
+        assert!(details.mounts.is_empty());
+        assert!(details.network_settings.ports.is_empty());
+    }
+
+    #[test]
+    fn null_descriptor_collections_deserialize_as_empty() {
+        let containers: Vec<ContainerDescriptor> = serde_json::from_str(
+            r#"[{"Id":"6fe66725ed81","Names":null,"Ports":null,"Mounts":null,
+                 "NetworkSettings":{"Networks":{}}}]"#,
+        )
+        .unwrap();
+        assert!(containers[0].names.is_empty());
+        assert!(containers[0].ports.is_empty());
+        assert!(containers[0].mounts.is_empty());
+    }
+
+    #[test]
+    fn health_before_first_probe_has_empty_log() {
+        let state: ContainerState = serde_json::from_str(
+            r#"{"Status":"running","Running":true,"ExitCode":0,
+                "Health":{"Status":"starting","FailingStreak":0,"Log":null}}"#,
+        )
+        .unwrap();
+        let health = state.health.unwrap();
+        assert_eq!(health.status, "starting");
+        assert!(health.log.is_empty());
+    }
 }